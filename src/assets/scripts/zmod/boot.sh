@@ -15,6 +15,11 @@ function _zle_infinite_line_finish() {
     __zsh_infinite_internal line-finish 2>/dev/null
 }
 
+# --- 端末の背景の再判定 (端末の配色を切り替えた後に呼ぶ) ---
+function zsh-infinite-refresh-background() {
+    __zsh_infinite_internal refresh-background
+}
+
 {
     autoload -Uz add-zsh-hook
     add-zsh-hook precmd _zsh_infinite_precmd
@@ -2,11 +2,104 @@
 mod modules;
 use crate::modules::zsh::theme::{self, prompt_theme::PromptTheme};
 pub use modules::*;
-use once_cell::sync::Lazy;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
 
-static PROMPT_THEME: Lazy<PromptTheme> = Lazy::new(theme::manager::load_theme);
+/// 読み込んだテーマと、その元になったファイル
+struct CachedTheme {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    theme: Arc<PromptTheme>,
+}
+
+impl CachedTheme {
+    fn is_fresh(&self, path: &Path) -> bool {
+        self.path == path && self.modified == modified(path)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
+
+/// ユーザーのテーマ。
+/// zmod ではプロセスが生き続けるため、テーマファイルのパスと更新時刻が同じ間だけ使い回す
+static USER_THEME: Mutex<Option<CachedTheme>> = Mutex::new(None);
+
+/// ディレクトリごとの上書き設定を重ねたテーマ。
+/// 上書き設定ファイルとその更新時刻、信頼状態、元のユーザーのテーマが同じ間だけ使い回す
+static LOCAL_THEME: Mutex<Option<(CachedTheme, bool, Arc<PromptTheme>)>> = Mutex::new(None);
+
+fn user_theme() -> Arc<PromptTheme> {
+    let path = theme::manager::get_theme_file_path().unwrap_or_default();
+    let mut cache = USER_THEME.lock().unwrap();
+    if let Some(cached) = cache.as_ref()
+        && cached.is_fresh(&path)
+    {
+        return cached.theme.clone();
+    }
+    let theme = Arc::new(theme::manager::load_theme());
+    *cache = Some(CachedTheme {
+        modified: modified(&path),
+        path,
+        theme: theme.clone(),
+    });
+    theme
+}
+
+/// カレントディレクトリで表示するテーマ。端末の背景に合わせた配色があればそれを使う
+fn prompt_theme() -> Arc<PromptTheme> {
+    let theme = directory_theme();
+    match theme::appearance::current() {
+        Some(appearance) if theme.has_variant(appearance) => {
+            let mut theme = (*theme).clone();
+            theme.apply_appearance(appearance);
+            Arc::new(theme)
+        }
+        _ => theme,
+    }
+}
 
-fn prompt_theme() -> &'static PromptTheme {
-    &PROMPT_THEME
+/// カレントディレクトリの上書き設定を重ねたテーマ
+fn directory_theme() -> Arc<PromptTheme> {
+    let user_theme = user_theme();
+    let Some(path) = env::current_dir()
+        .ok()
+        .and_then(|cwd| theme::local::find_local_theme(&cwd))
+    else {
+        return user_theme;
+    };
+    // 信頼されていない設定は読まずに、警告だけを表示する
+    let trusted = theme::trust::is_trusted(&path);
+    let mut cache = LOCAL_THEME.lock().unwrap();
+    if let Some((cached, cached_trusted, base)) = cache.as_ref()
+        && cached.is_fresh(&path)
+        && *cached_trusted == trusted
+        && Arc::ptr_eq(base, &user_theme)
+    {
+        return cached.theme.clone();
+    }
+    let theme = Arc::new(if trusted {
+        theme::local::apply_local_theme(&user_theme, &path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            (*user_theme).clone()
+        })
+    } else {
+        theme::local::untrusted_theme(&user_theme)
+    });
+    *cache = Some((
+        CachedTheme {
+            modified: modified(&path),
+            path,
+            theme: theme.clone(),
+        },
+        trusted,
+        user_theme,
+    ));
+    theme
 }
 mod zmod;
@@ -4,6 +4,10 @@ use zsh_infinite::{args, utils, zsh};
 #[tokio::main]
 async fn main() {
     let args = args::Args::parse();
+    if let Some(config) = args.config {
+        let config = std::path::absolute(&config).unwrap_or(config);
+        zsh::theme::manager::set_config_path(Some(config));
+    }
     match args.command {
         args::Commands::Zsh { command } => zsh::main(command).await,
         args::Commands::Update => utils::update(),
@@ -18,5 +22,6 @@ async fn main() {
                 zsh::theme::main().await
             }
         }
+        args::Commands::Trust { command } => zsh::theme::trust::run(command),
     }
 }
@@ -1,5 +1,10 @@
 use clap::{Parser, Subcommand};
-use std::cmp::Ordering;
+use std::{cmp::Ordering, path::PathBuf};
+
+use crate::zsh::theme::{
+    generate::Scheme, import::ImportSource, manager::ThemeFormat, prompt_theme::PromptTheme,
+    terminal_palette::PaletteFormat,
+};
 
 #[derive(Parser)]
 #[command(
@@ -12,6 +17,11 @@ pub struct Args {
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
+    /// Theme file to use instead of the one in the config directory
+    /// (also settable with ZSH_INFINITE_CONFIG)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -43,6 +53,29 @@ pub enum Commands {
         #[command(subcommand)]
         command: Option<ThemeCommands>,
     },
+    /// Manage trusted per-directory configuration files
+    Trust {
+        #[command(subcommand)]
+        command: TrustCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TrustCommands {
+    /// Trust a directory's .zsh-infinite.yaml in its current state
+    Add {
+        /// Directory or configuration file
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Stop trusting a directory's .zsh-infinite.yaml
+    Remove {
+        /// Directory or configuration file
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// List trusted configuration files
+    List,
 }
 
 #[derive(Subcommand)]
@@ -51,6 +84,119 @@ pub enum ThemeCommands {
     Default,
     /// Theme for The Infinity's
     Infinite,
+    /// Validate a theme file and report every problem found
+    Check {
+        /// Theme file to check (defaults to the active theme file)
+        path: Option<PathBuf>,
+    },
+    /// Print a JSON Schema describing theme.yaml
+    Schema,
+    /// Convert a theme file to another format (the original is kept as .bak)
+    Convert {
+        /// Target format
+        #[arg(long)]
+        to: ThemeFormat,
+        /// Theme file to convert (defaults to the active theme file)
+        path: Option<PathBuf>,
+    },
+    /// Upgrade a theme file written for an older version (the original is kept as .bak)
+    Migrate {
+        /// Theme file to migrate (defaults to the active theme file)
+        path: Option<PathBuf>,
+    },
+    /// Import a theme share string, or a Starship or Powerlevel10k configuration with --from
+    Import {
+        /// Kind of configuration to import (without it, INPUT is a share string)
+        #[arg(long)]
+        from: Option<ImportSource>,
+        /// Share string from `theme export --share`, or the starship.toml / .p10k.zsh to import
+        input: String,
+        /// Write the theme to this file instead of the active theme file
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Import a terminal colour scheme as palette entries and prompt colours
+    ImportPalette {
+        /// base16/base24 YAML, Alacritty TOML, Kitty .conf or iTerm2 .itermcolors file
+        file: PathBuf,
+        /// Format of FILE (detected from the extension by default)
+        #[arg(long)]
+        format: Option<PaletteFormat>,
+        /// Write the theme to this file instead of the active theme file
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Apply without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Compile the theme into a standalone zsh prompt script
+    Compile {
+        /// Theme file to compile (defaults to the active theme file)
+        path: Option<PathBuf>,
+        /// Write the script to this file instead of standard output
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Print the theme as YAML, or as a compact share string with --share
+    Export {
+        /// Print a compressed share string that `theme import` accepts
+        #[arg(long)]
+        share: bool,
+        /// Theme file to export (defaults to the active theme file)
+        path: Option<PathBuf>,
+    },
+    /// List the built-in presets with a preview of each
+    ListPresets,
+    /// Replace the theme with a built-in preset
+    Apply {
+        /// Preset name
+        #[arg(value_parser = PromptTheme::PRESETS)]
+        preset: String,
+    },
+    /// Generate colours for every prompt line from a seed colour
+    Generate {
+        /// Seed colour such as '#00FFFF'
+        #[arg(long, required_unless_present = "random")]
+        seed: Option<String>,
+        /// How the accent hues are spread around the seed
+        #[arg(long, value_enum, default_value_t)]
+        scheme: Scheme,
+        /// Colours for a dark terminal background (the default)
+        #[arg(long, conflicts_with = "light")]
+        dark: bool,
+        /// Colours for a light terminal background
+        #[arg(long)]
+        light: bool,
+        /// Use a random seed colour
+        #[arg(long, conflicts_with = "seed")]
+        random: bool,
+        /// Apply without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// List saved theme profiles (the active one is marked with *)
+    List,
+    /// Switch to a saved theme profile
+    Use {
+        /// Profile name
+        name: String,
+    },
+    /// Save the current theme as a named profile and switch to it
+    SaveAs {
+        /// Profile name
+        name: String,
+        /// File format of the profile (defaults to the current theme's format)
+        #[arg(long)]
+        format: Option<ThemeFormat>,
+    },
+    /// Delete a saved theme profile
+    Delete {
+        /// Profile name
+        name: String,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
 }
 #[derive(Subcommand)]
 pub enum BashCommands {}
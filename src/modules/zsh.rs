@@ -2,14 +2,14 @@ use crate::args::{PromptType, ZshCommands};
 
 mod prompt;
 pub mod theme;
+use theme::color_depth::ColorDepth;
 pub use theme::manager::{load_theme, save_theme};
 use zsh_seq::ZshPromptBuilder;
 
 pub async fn main(command: ZshCommands) {
     match command {
         ZshCommands::Prompt { side } => {
-            let builder = build_prompt(&side).await;
-            print!("{}", builder.build())
+            print!("{}", render_prompt(&side, ColorDepth::detect()).await)
         }
         ZshCommands::BuildIn { segment } => {
             prompt::segment(*segment);
@@ -24,3 +24,8 @@ pub async fn build_prompt(prompt_type: &PromptType) -> ZshPromptBuilder {
         PromptType::Transient { exit_code } => prompt::transient(exit_code).await,
     }
 }
+
+/// プロンプトを組み立て、端末の色数で表示できる色にした文字列を返す
+pub async fn render_prompt(prompt_type: &PromptType, depth: ColorDepth) -> String {
+    depth.quantize_prompt(&build_prompt(prompt_type).await.build())
+}
@@ -8,13 +8,62 @@ pub use left::left;
 pub use right::right;
 pub use segment::segment;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{fmt, sync::Mutex};
 pub use transient::transient;
-use zsh_seq::{ZshPromptBuilder, ZshSequence};
+use zsh_seq::{NamedColor, ZshPromptBuilder, ZshSequence};
 
-use crate::zsh::theme::prompt_theme::PromptContents;
+use crate::zsh::theme::{
+    color_scheme::{AccentColor, PromptColorScheme},
+    contrast,
+    prompt_theme::{AccentScope, PromptContents, PromptTheme},
+    rainbow::DynamicHues,
+};
+
+/// `min_contrast` が設定されていれば、背景 `bg` の上で読めるようセグメントの文字色を直す
+fn segment_text(
+    content: &[ZshSequence],
+    bg: NamedColor,
+    prompt_contents: &PromptContents,
+) -> Vec<ZshSequence> {
+    match prompt_contents.min_contrast {
+        Some(min) => contrast::apply(content, bg, prompt_contents.color.fg, min),
+        None => content.to_vec(),
+    }
+}
 
 impl Prompt {
+    /// 虹の動的なずれを `hues` に固定して描画する空の行
+    pub fn new(hues: DynamicHues) -> Self {
+        Self {
+            left: Vec::new(),
+            right: Vec::new(),
+            accent_range: None,
+            hues,
+        }
+    }
+    /// 行の中でアクセントの色を取る位置の数
+    fn accent_slots(&self) -> usize {
+        self.total_separation() + 1
+    }
+    /// 行の中で `index` 番目、位置 `progress` (0.0〜1.0) のアクセントの色。
+    /// プロンプト全体の範囲が設定されていれば、その範囲に写して取る
+    fn accent(&self, color_scheme: &PromptColorScheme, index: usize, progress: f32) -> NamedColor {
+        match &self.accent_range {
+            Some(range) => {
+                let index = if range.first_index <= range.last_index {
+                    range.first_index + index
+                } else {
+                    range.first_index.saturating_sub(index)
+                };
+                range.accent.at(
+                    index,
+                    range.start + (range.end - range.start) * progress,
+                    &self.hues,
+                )
+            }
+            None => color_scheme.accent.at(index, progress, &self.hues),
+        }
+    }
     fn left_separation(&self) -> usize {
         if self.left.is_empty() {
             0
@@ -51,7 +100,7 @@ impl Prompt {
 
         // 開始キャップ (edge_cap が有効なら Box を描画)
         if seps.edge_cap {
-            let start_color = color_scheme.accent.get(0.0);
+            let start_color = self.accent(color_scheme, 0, 0.0);
             builder = builder
                 .color(start_color)
                 .str(&seps.start_separator.sep_box().right)
@@ -72,11 +121,11 @@ impl Prompt {
         for (i, content) in self.left.iter().enumerate() {
             builder = builder
                 .color_bg(bg_color)
-                .chain(content.to_owned())
+                .chain(segment_text(content, bg_color, prompt_contents))
                 .end_color_bg();
             if i < len - 1 {
                 let color_pos = (i + 1) as f32 / total;
-                let sep_color = color_scheme.accent.get(color_pos);
+                let sep_color = self.accent(color_scheme, i + 1, color_pos);
 
                 if seps.bold_separation {
                     // 太い区切りの場合は背景色を切り替えつつ Box を使用
@@ -103,9 +152,11 @@ impl Prompt {
 
         // 終了キャップ
         if seps.edge_cap {
-            let end_color = color_scheme
-                .accent
-                .get(self.left_separation() as f32 / total);
+            let end_color = self.accent(
+                color_scheme,
+                self.left.len(),
+                self.left_separation() as f32 / total,
+            );
             builder = builder
                 .color(bg_color)
                 .color_bg(end_color)
@@ -137,7 +188,7 @@ impl Prompt {
 
         let mut builder = ZshPromptBuilder::new();
 
-        let start_color = color_scheme.accent.get(0.0);
+        let start_color = self.accent(color_scheme, 0, 0.0);
         if seps.edge_cap {
             builder = builder
                 .color(bg_color)
@@ -158,15 +209,15 @@ impl Prompt {
         let len = self.left.len();
         for (i, content) in self.left.iter().enumerate() {
             let color_pos = i as f32 / total;
-            let sep_color = color_scheme.accent.get(color_pos);
+            let sep_color = self.accent(color_scheme, i, color_pos);
             builder = builder
                 .color_bg(sep_color)
-                .chain(content.to_owned())
+                .chain(segment_text(content, sep_color, prompt_contents))
                 .end_color_bg();
             if i < len - 1 {
                 if seps.bold_separation {
                     let next_color_pos = (i + 1) as f32 / total;
-                    let next_sep_color = color_scheme.accent.get(next_color_pos);
+                    let next_sep_color = self.accent(color_scheme, i + 1, next_color_pos);
                     // 太い区切りの場合は背景色を切り替えつつ Box を使用
                     builder = builder
                         .color(sep_color)
@@ -189,9 +240,11 @@ impl Prompt {
         }
 
         // 終了キャップ
-        let end_color = color_scheme
-            .accent
-            .get((self.left.len() - 1) as f32 / total);
+        let end_color = self.accent(
+            color_scheme,
+            self.left.len() - 1,
+            (self.left.len() - 1) as f32 / total,
+        );
 
         if seps.edge_cap {
             builder = builder
@@ -226,7 +279,7 @@ impl Prompt {
         // 右側の開始キャップ
         if seps.edge_cap {
             let start_pos = (self.left_separation() + 1) as f32 / total;
-            let start_color = color_scheme.accent.get(start_pos);
+            let start_color = self.accent(color_scheme, self.left_separation(), start_pos);
             builder = builder
                 .color(start_color)
                 .str(&seps.start_separator.sep_box().right)
@@ -247,12 +300,13 @@ impl Prompt {
         for (i, content) in self.right.iter().enumerate() {
             builder = builder
                 .color_bg(bg_color)
-                .chain(content.to_owned())
+                .chain(segment_text(content, bg_color, prompt_contents))
                 .end_color_bg();
 
             if i < len - 1 {
                 let color_pos = (self.left_separation() + i + 2) as f32 / total;
-                let sep_color = color_scheme.accent.get(color_pos);
+                let sep_color =
+                    self.accent(color_scheme, self.left_separation() + i + 1, color_pos);
 
                 if seps.bold_separation {
                     builder = builder
@@ -277,7 +331,8 @@ impl Prompt {
 
         // 右端のキャップ
         if seps.edge_cap {
-            let end_color = color_scheme.accent.get(1.0);
+            let end_color =
+                self.accent(color_scheme, self.left_separation() + self.right.len(), 1.0);
             builder = builder
                 .color(bg_color)
                 .color_bg(end_color)
@@ -309,7 +364,7 @@ impl Prompt {
         let mut builder = ZshPromptBuilder::new();
 
         let start_pos = (self.left_separation() + 1) as f32 / total;
-        let start_color = color_scheme.accent.get(start_pos);
+        let start_color = self.accent(color_scheme, self.left.len(), start_pos);
         // 右側の開始キャップ
         if seps.edge_cap {
             builder = builder
@@ -331,14 +386,15 @@ impl Prompt {
         let len = self.right.len();
         for (i, content) in self.right.iter().enumerate() {
             let color_pos = (self.left_separation() + i + 1) as f32 / total;
-            let sep_color = color_scheme.accent.get(color_pos);
+            let sep_color = self.accent(color_scheme, self.left.len() + i, color_pos);
             builder = builder
                 .color_bg(sep_color)
-                .chain(content.to_owned())
+                .chain(segment_text(content, sep_color, prompt_contents))
                 .end_color_bg();
             if i < len - 1 {
                 let next_color_pos = (self.left_separation() + i + 2) as f32 / total;
-                let next_sep_color = color_scheme.accent.get(next_color_pos);
+                let next_sep_color =
+                    self.accent(color_scheme, self.left.len() + i + 1, next_color_pos);
                 if seps.bold_separation {
                     builder = builder
                         .color(bg_color)
@@ -361,7 +417,11 @@ impl Prompt {
         }
 
         // 右端のキャップ
-        let end_color = color_scheme.accent.get(1.0 - 1.0 / total);
+        let end_color = self.accent(
+            color_scheme,
+            self.left.len() + self.right.len() - 1,
+            1.0 - 1.0 / total,
+        );
         if seps.edge_cap {
             builder = builder
                 .color(end_color)
@@ -403,10 +463,87 @@ impl Prompt {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct Prompt {
     left: Vec<Vec<ZshSequence>>,
     right: Vec<Vec<ZshSequence>>,
+    /// プロンプト全体でアクセントを進める場合の、この行の範囲
+    accent_range: Option<AccentRange>,
+    /// この描画での虹の動的なずれ
+    hues: DynamicHues,
+}
+
+/// プロンプト全体のアクセントのうち、1 行が使う範囲。`start` > `end` なら右から左へ進む。
+/// `first_index` と `last_index` は `Cycle` や `PerSegment` で使う区切りの番号の範囲
+#[derive(Clone)]
+struct AccentRange {
+    accent: AccentColor,
+    start: f32,
+    end: f32,
+    first_index: usize,
+    last_index: usize,
+}
+
+/// 最後に描画した左プロンプトでの、下端の枠と transient prompt の区切りの番号。
+/// zsh モジュールでは右プロンプトと transient prompt が同じプロセスで後から描画されるので、これに揃える
+static ACCENT_TAIL_INDEX: Mutex<Option<usize>> = Mutex::new(None);
+
+/// 下端の枠と transient prompt のアクセントに使う区切りの番号。
+/// 左プロンプトをまだ描画していなければ、全てのセグメントが表示されるものとして数える
+pub fn accent_tail_index(theme: &PromptTheme) -> usize {
+    ACCENT_TAIL_INDEX
+        .lock()
+        .ok()
+        .and_then(|index| *index)
+        .unwrap_or_else(|| {
+            theme
+                .prompt_contents_list
+                .iter()
+                .map(|contents| {
+                    let separation = |segments: usize| match segments {
+                        0 => 0,
+                        n => n + 1,
+                    };
+                    separation(contents.left.len()) + separation(contents.right.len()) + 1
+                })
+                .sum()
+        })
+}
+
+/// `accent_scope` が `Prompt` か `Snake` なら、各行にプロンプト全体での範囲を設定する。
+/// 行の位置の数に比例して分け、最後の位置は下端の枠と transient prompt のために空けておく。
+/// その最後の位置の番号を返す
+pub fn apply_accent_scope(theme: &PromptTheme, prompts: &mut [Prompt]) -> usize {
+    let tail = prompts.iter().map(Prompt::accent_slots).sum::<usize>();
+    if let Ok(mut index) = ACCENT_TAIL_INDEX.lock() {
+        *index = Some(tail);
+    }
+    if theme.accent_scope == AccentScope::Line {
+        return tail;
+    }
+    let Some(first) = theme.prompt_contents_list.first() else {
+        return tail;
+    };
+    let last = tail as f32;
+    let mut offset = 0;
+    for (i, prompt) in prompts.iter_mut().enumerate() {
+        let slots = prompt.accent_slots();
+        let (mut start, mut end) = (offset as f32 / last, (offset + slots - 1) as f32 / last);
+        let (mut first_index, mut last_index) = (offset, offset + slots - 1);
+        if theme.accent_scope == AccentScope::Snake && i % 2 == 1 {
+            (start, end) = (end, start);
+            (first_index, last_index) = (last_index, first_index);
+        }
+        prompt.accent_range = Some(AccentRange {
+            accent: first.color.accent.clone(),
+            start,
+            end,
+            first_index,
+            last_index,
+        });
+        offset += slots;
+    }
+    tail
 }
 #[derive(Clone, Default, Copy, Debug, Serialize, Deserialize, PartialEq)]
 pub enum PromptConnection {
@@ -425,6 +562,23 @@ pub enum PromptConnection {
     Gradient, // グラデーション (░▒▓)
 }
 
+impl PromptConnection {
+    pub const ALL: [Self; 12] = [
+        Self::None,
+        Self::Line,
+        Self::Double,
+        Self::Bold,
+        Self::Dashed,
+        Self::Dotted,
+        Self::Dot,
+        Self::Bullet,
+        Self::Wave,
+        Self::ZigZag,
+        Self::Bar,
+        Self::Gradient,
+    ];
+}
+
 impl fmt::Display for PromptConnection {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let s = match self {
@@ -444,16 +598,16 @@ impl fmt::Display for PromptConnection {
         write!(f, "{}", s)
     }
 }
-struct PromptCurveLine {
-    top_left: String,
-    top_right: String,
-    bottom_left: String,
-    bottom_right: String,
-    horizontal: String, // 横線 ─
+pub struct PromptCurveLine {
+    pub top_left: String,
+    pub top_right: String,
+    pub bottom_left: String,
+    pub bottom_right: String,
+    pub horizontal: String, // 横線 ─
     #[allow(unused)]
-    vertical: String, // 縦線 │
-    cross_left: String, // 縦線から右に枝分かれ ├
-    cross_right: String,
+    pub vertical: String, // 縦線 │
+    pub cross_left: String, // 縦線から右に枝分かれ ├
+    pub cross_right: String,
 }
 impl Default for PromptCurveLine {
     fn default() -> Self {
@@ -580,6 +734,18 @@ impl From<PromptSeparation> for PromptSeparationLine {
     }
 }
 impl PromptSeparation {
+    pub const ALL: [Self; 10] = [
+        Self::Block,
+        Self::Sharp,
+        Self::Slash,
+        Self::BackSlash,
+        Self::Round,
+        Self::Blur,
+        Self::Flame,
+        Self::Pixel,
+        Self::Wave,
+        Self::Lego,
+    ];
     pub fn sep_box(&self) -> PromptSeparationBox {
         match self {
             Self::Block => PromptSeparationBox::new(" ", " "),
@@ -1,6 +1,9 @@
 use crate::{
     prompt_theme,
-    zsh::prompt::{Prompt, PromptConnection, PromptCurveLine},
+    zsh::{
+        prompt::{Prompt, PromptConnection, PromptCurveLine, apply_accent_scope},
+        theme::rainbow::DynamicHues,
+    },
 };
 use crossterm::terminal;
 use futures::future::join_all;
@@ -30,12 +33,11 @@ pub async fn left() -> ZshPromptBuilder {
     }
 
     // 2. リストがある場合のメインループ
-    for (i, prompt_contents) in theme.prompt_contents_list.iter().enumerate() {
-        let mut prompt = Prompt::default();
-        let curved_lines = PromptCurveLine::from(prompt_contents.connection);
-        let h = &curved_lines.horizontal;
-
-        // (非同期取得部分は変更なし)
+    // アクセントをプロンプト全体で進める場合に備え、先に全ての行の中身を取得する
+    let hues = DynamicHues::now();
+    let mut prompts = Vec::with_capacity(theme.prompt_contents_list.len());
+    for prompt_contents in &theme.prompt_contents_list {
+        let mut prompt = Prompt::new(hues);
         let left_futures: Vec<_> = prompt_contents
             .left
             .iter()
@@ -55,7 +57,15 @@ pub async fn left() -> ZshPromptBuilder {
             .collect();
         prompt.extend_left(left_results);
         prompt.extend_right(right_results);
+        prompts.push(prompt);
+    }
+    let tail = apply_accent_scope(&theme, &mut prompts);
 
+    for (i, (prompt_contents, prompt)) in
+        theme.prompt_contents_list.iter().zip(&prompts).enumerate()
+    {
+        let curved_lines = PromptCurveLine::from(prompt_contents.connection);
+        let h = &curved_lines.horizontal;
         let left_content = prompt.render_left(prompt_contents);
         let right_content = prompt.render_right(prompt_contents);
         let terminal_width = terminal::size().map(|(w, _)| w).unwrap_or(80) as usize;
@@ -107,7 +117,7 @@ pub async fn left() -> ZshPromptBuilder {
     let curved_lines = PromptCurveLine::from(connection);
     let h = &curved_lines.horizontal;
     let end = ZshPromptBuilder::new()
-        .color(sc)
+        .color(theme.accent_tail(tail, &hues).unwrap_or(sc))
         .str(&curved_lines.bottom_left)
         .str(h)
         .str(" ")
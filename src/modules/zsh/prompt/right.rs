@@ -2,7 +2,10 @@ use zsh_seq::{NamedColor, ZshPromptBuilder};
 
 use crate::{
     prompt_theme,
-    zsh::prompt::{PromptConnection, PromptCurveLine},
+    zsh::{
+        prompt::{PromptConnection, PromptCurveLine, accent_tail_index},
+        theme::rainbow::DynamicHues,
+    },
 };
 
 pub async fn right() -> ZshPromptBuilder {
@@ -15,7 +18,11 @@ pub async fn right() -> ZshPromptBuilder {
     let h = &curved_lines.horizontal;
 
     ZshPromptBuilder::new()
-        .color(sc)
+        .color(
+            theme
+                .accent_tail(accent_tail_index(&theme), &DynamicHues::now())
+                .unwrap_or(sc),
+        )
         .str(h)
         .str(&curved_lines.bottom_right)
         .end_color()
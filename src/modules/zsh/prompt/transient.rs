@@ -1,12 +1,18 @@
 use zsh_seq::ZshPromptBuilder;
 
-use crate::prompt_theme;
+use crate::{
+    prompt_theme,
+    zsh::{prompt::accent_tail_index, theme::rainbow::DynamicHues},
+};
 
 pub async fn transient(exit_code: &Option<i32>) -> ZshPromptBuilder {
     let transient_str = "❯ ";
     let theme = prompt_theme();
     let color = match exit_code {
-        Some(0) => theme.transient_color.pc,
+        // アクセントをプロンプト全体で進める場合は、その続きの色にする
+        Some(0) => theme
+            .accent_tail(accent_tail_index(&theme), &DynamicHues::now())
+            .unwrap_or(theme.transient_color.pc),
         _ => theme.transient_color.sc,
     };
     ZshPromptBuilder::new()
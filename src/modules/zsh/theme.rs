@@ -1,16 +1,39 @@
+pub mod appearance;
+pub mod check;
+pub mod color_depth;
 mod color_named_color;
+pub mod color_parse;
 pub mod color_scheme;
+pub mod compile;
 pub mod config_ui;
+pub mod contrast;
+pub mod extends;
+pub mod generate;
 pub mod gradient;
+pub mod import;
+pub mod local;
 pub mod manager;
+pub mod migrate;
 pub mod named_color_serde; // 既存のファイルをそのまま使用
 pub mod named_color_serde_option; // 新しく追加
+pub mod palette;
+pub mod per_segment;
+pub mod presets;
+pub mod preview;
+pub mod profile;
 pub mod prompt_theme;
+pub mod rainbow;
+pub mod schema;
+pub mod share;
+pub mod terminal_palette;
+pub mod trust;
 use dialoguer::Select;
 use dialoguer::theme::ColorfulTheme;
+use std::path::Path;
 
 use crate::{
     args::ThemeCommands,
+    zsh::theme::manager::ThemeFormat,
     zsh::theme::prompt_theme::{PromptContent, PromptContents, PromptTheme},
 };
 
@@ -18,9 +41,143 @@ pub fn set(theme: ThemeCommands) {
     let theme = match theme {
         ThemeCommands::Default => PromptTheme::default(),
         ThemeCommands::Infinite => PromptTheme::infinite(),
+        ThemeCommands::Check { path } => {
+            if !check::check(path.as_deref()) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        ThemeCommands::Schema => {
+            schema::print_schema();
+            return;
+        }
+        ThemeCommands::Convert { to, path } => {
+            match manager::convert_theme(path.as_deref(), to) {
+                Ok(target) => println!("Theme converted to: {:?}", target),
+                Err(e) => {
+                    eprintln!("Failed to convert theme: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        ThemeCommands::Migrate { path } => {
+            match manager::migrate_theme(path.as_deref()) {
+                Ok(Some(backup)) => println!(
+                    "Theme migrated to version {}. The original was kept as {:?}.",
+                    migrate::CURRENT_VERSION,
+                    backup
+                ),
+                Ok(None) => println!("Theme is already up to date."),
+                Err(e) => {
+                    eprintln!("Failed to migrate theme: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        ThemeCommands::Import { from, input, out } => {
+            let result = match from {
+                Some(from) => import::run(from, Path::new(&input), out.as_deref()),
+                None => share::import(&input, out.as_deref()),
+            };
+            match result {
+                Ok(Some(path)) => println!("Theme imported to: {:?}", path),
+                Ok(None) => println!("Import cancelled."),
+                Err(e) => {
+                    eprintln!("Failed to import {:?}: {}", input, e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        ThemeCommands::ImportPalette {
+            file,
+            format,
+            out,
+            yes,
+        } => {
+            match terminal_palette::run(&file, format, out.as_deref(), yes) {
+                Ok(Some(path)) => println!("Palette imported to: {:?}", path),
+                Ok(None) => println!("Import cancelled."),
+                Err(e) => {
+                    eprintln!("Failed to import palette: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        ThemeCommands::Export { share, path } => {
+            let result = if share {
+                share::export(path.as_deref())
+            } else {
+                manager::load_theme_from(path.as_deref()).and_then(|theme| {
+                    let yaml = ThemeFormat::Yaml
+                        .serialize(&theme)
+                        .map_err(std::io::Error::other)?;
+                    print!("{}", yaml);
+                    Ok(())
+                })
+            };
+            exit_on_error(result);
+            return;
+        }
+        ThemeCommands::Compile { path, out } => {
+            match compile::run(path.as_deref(), out.as_deref()) {
+                Ok(Some(out)) => println!("Prompt script written to: {:?}", out),
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Failed to compile theme: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        ThemeCommands::ListPresets => {
+            presets::list();
+            return;
+        }
+        ThemeCommands::Apply { preset } => {
+            exit_on_error(presets::apply(&preset));
+            return;
+        }
+        ThemeCommands::Generate {
+            seed,
+            scheme,
+            dark: _,
+            light,
+            random: _,
+            yes,
+        } => {
+            // `--dark` は既定と同じなので、`--light` が無ければ暗い背景向けにする
+            exit_on_error(generate::run(seed.as_deref(), scheme, !light, yes));
+            return;
+        }
+        ThemeCommands::List => {
+            profile::list();
+            return;
+        }
+        ThemeCommands::Use { name } => {
+            exit_on_error(profile::use_profile(&name));
+            return;
+        }
+        ThemeCommands::SaveAs { name, format } => {
+            exit_on_error(profile::save_as(&name, format));
+            return;
+        }
+        ThemeCommands::Delete { name, yes } => {
+            exit_on_error(profile::delete(&name, yes));
+            return;
+        }
     };
     let _ = manager::save_theme(&theme);
 }
+fn exit_on_error(result: std::io::Result<()>) {
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
 pub async fn main() {
     let mut current_theme = manager::load_theme();
     loop {
@@ -34,6 +191,10 @@ pub async fn main() {
         for (i, _) in current_theme.prompt_contents_list.iter().enumerate() {
             options.push(format!("Configure Prompt Line {}", i));
         }
+        options.push(format!(
+            "Accent Scope (current: {:?})",
+            current_theme.accent_scope
+        ));
         options.push("Save and Exit".to_string());
 
         let selection = Select::with_theme(&ColorfulTheme::default())
@@ -58,7 +219,7 @@ pub async fn main() {
                     println!("No prompt lines to remove.");
                 }
             }
-            s if s >= 2 && s < options.len() - 1 => {
+            s if s >= 2 && s < options.len() - 2 => {
                 // Configure Prompt Line
                 let line_index = s - 2;
                 if let Some(prompt_contents) =
@@ -69,6 +230,10 @@ pub async fn main() {
                     eprintln!("Invalid prompt line index selected.");
                 }
             }
+            s if s == options.len() - 2 => {
+                current_theme.accent_scope =
+                    config_ui::select_accent_scope(current_theme.accent_scope);
+            }
             s if s == options.len() - 1 => {
                 // Save and Exit
                 let _ = manager::save_theme(&current_theme);
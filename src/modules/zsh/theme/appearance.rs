@@ -0,0 +1,155 @@
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{Read, Write},
+    os::fd::AsRawFd,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::gradient::rgb_to_oklab;
+
+/// 背景の明暗の自動判定を上書きする環境変数。`light` か `dark`
+pub const BACKGROUND_ENV: &str = "ZSH_INFINITE_BACKGROUND";
+
+/// 端末の問い合わせに応答を待つ時間。
+/// これより遅れて届いた応答は読み捨てられず、端末の入力 (zsh の入力行など) に混ざってしまう。
+/// 入力された文字と区別できないので、待ち時間を延ばす以外に防ぐ方法は無い
+const QUERY_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// 端末の背景の明暗
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+/// zmod が判定した背景。`set_appearance` で設定する
+static APPEARANCE: Mutex<Option<Appearance>> = Mutex::new(None);
+
+impl Appearance {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "light" => Some(Self::Light),
+            "dark" => Some(Self::Dark),
+            _ => None,
+        }
+    }
+
+    fn from_rgb(rgb: (u8, u8, u8)) -> Self {
+        // OKLab の明度で中間の灰色より明るければ明るい背景とみなす
+        if rgb_to_oklab(rgb).0 > 0.6 {
+            Self::Light
+        } else {
+            Self::Dark
+        }
+    }
+}
+
+/// 描画に使う背景の明暗を設定する。`None` で環境変数からの判定に戻す
+pub fn set_appearance(appearance: Option<Appearance>) {
+    *APPEARANCE.lock().unwrap() = appearance;
+}
+
+/// 描画に使う背景の明暗。`set_appearance` の指定、環境変数からの判定の順に見る
+pub fn current() -> Option<Appearance> {
+    APPEARANCE
+        .lock()
+        .unwrap()
+        .or_else(|| detect_with(|name| env::var(name).ok(), None))
+}
+
+/// 背景の明暗を判定する。
+/// `ZSH_INFINITE_BACKGROUND`、端末への問い合わせの結果 `queried`、`COLORFGBG` の順に見る
+pub fn detect_with(
+    var: impl Fn(&str) -> Option<String>,
+    queried: Option<Appearance>,
+) -> Option<Appearance> {
+    var(BACKGROUND_ENV)
+        .as_deref()
+        .and_then(Appearance::parse)
+        .or(queried)
+        .or_else(|| var("COLORFGBG").as_deref().and_then(from_colorfgbg))
+}
+
+/// `COLORFGBG` (`15;0` のような前景色と背景色の番号) から判定する
+fn from_colorfgbg(value: &str) -> Option<Appearance> {
+    let bg = value.rsplit(';').next()?.trim().parse::<u8>().ok()?;
+    Some(match bg {
+        7 | 9..=15 => Appearance::Light,
+        _ => Appearance::Dark,
+    })
+}
+
+/// OSC 11 で端末に背景色を問い合わせる。応答が無い端末では `None`
+pub fn query_background() -> Option<Appearance> {
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let fd = tty.as_raw_fd();
+
+    // 応答が画面に表示されず、改行を待たずに読めるようにする
+    // SAFETY: termios は整数と配列だけの C の構造体なので、全て 0 でも有効な値になる
+    let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+    // SAFETY: fd は `tty` が開いている間有効で、`original` は書き込める termios を指す
+    if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+        return None;
+    }
+    let mut raw = original;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+    raw.c_cc[libc::VMIN] = 0;
+    raw.c_cc[libc::VTIME] = 0;
+    // SAFETY: fd は `tty` が開いている間有効で、`raw` は tcgetattr で得た設定を変えたもの
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+        return None;
+    }
+
+    let response = tty
+        .write_all(b"\x1b]11;?\x1b\\")
+        .and_then(|_| tty.flush())
+        .ok()
+        .and_then(|_| read_response(&mut tty, fd));
+
+    // SAFETY: fd はまだ有効で、`original` は tcgetattr で得た元の設定
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    parse_osc11(&response?).map(Appearance::from_rgb)
+}
+
+/// BEL か ST で終わる応答を、`QUERY_TIMEOUT` まで待って読む
+fn read_response(tty: &mut std::fs::File, fd: i32) -> Option<String> {
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut response = Vec::new();
+    let mut buf = [0u8; 64];
+    loop {
+        let remaining = deadline.checked_duration_since(Instant::now())?;
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `pollfd` は 1 つの有効な pollfd を指し、呼び出しの間だけ使われる
+        if unsafe { libc::poll(&mut pollfd, 1, remaining.as_millis() as i32) } <= 0 {
+            return None;
+        }
+        let n = tty.read(&mut buf).ok()?;
+        response.extend_from_slice(&buf[..n]);
+        if response.ends_with(b"\x07") || response.ends_with(b"\x1b\\") {
+            return String::from_utf8(response).ok();
+        }
+    }
+}
+
+/// `ESC ] 11 ; rgb:RRRR/GGGG/BBBB` の色を読む。各成分は 1〜4 桁の 16 進数
+fn parse_osc11(response: &str) -> Option<(u8, u8, u8)> {
+    let rgb = response.split("rgb:").nth(1)?;
+    let rgb = rgb.trim_end_matches(['\x07', '\\', '\x1b']);
+    let mut channels = rgb.split('/').map(|channel| {
+        let digits = channel.len() as u32;
+        let value = u32::from_str_radix(channel, 16).ok()?;
+        let max = 16u32.checked_pow(digits)?.checked_sub(1)?;
+        (max > 0).then(|| (value * 255 / max) as u8)
+    });
+    Some((channels.next()??, channels.next()??, channels.next()??))
+}
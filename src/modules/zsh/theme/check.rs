@@ -0,0 +1,877 @@
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    path::Path,
+};
+use yaml_rust2::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+use super::{
+    color_scheme::{AccentColor, PromptColorScheme},
+    contrast::CONTRAST_RANGE,
+    extends,
+    gradient::{Interpolation, parse_gradient_stop},
+    manager::{self, ThemeFormat},
+    migrate, named_color_serde, named_color_serde_option, palette,
+    prompt_theme::{
+        AccentScope, AccentWhich, PromptContent, PromptContents, PromptSegmentSeparators,
+        PromptTheme,
+    },
+    rainbow::{HueDirection, HueOffset},
+};
+use crate::zsh::prompt::{PromptConnection, PromptSeparation};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// テーマファイル内の1つの問題
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// `prompt_contents_list.0.color.bg` のようなドット区切りのパス
+    pub path: String,
+    pub message: String,
+    /// 1始まりの (行, 列)。位置が特定できない場合は None
+    pub location: Option<(usize, usize)>,
+    /// 問題の値が継承元にある場合、そのファイルのパスかプリセット名
+    pub source: Option<String>,
+}
+
+struct Diagnostics {
+    items: Vec<Diagnostic>,
+    format: ThemeFormat,
+}
+
+impl Diagnostics {
+    fn push(&mut self, severity: Severity, path: &str, message: impl Into<String>) {
+        self.items.push(Diagnostic {
+            severity,
+            path: path.to_string(),
+            message: message.into(),
+            location: None,
+            source: None,
+        });
+    }
+    fn error(&mut self, path: &str, message: impl Into<String>) {
+        self.push(Severity::Error, path, message);
+    }
+    fn warning(&mut self, path: &str, message: impl Into<String>) {
+        self.push(Severity::Warning, path, message);
+    }
+    fn error_count(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+}
+
+/// テーマファイルを検証し、結果を表示する。エラーが無ければ true を返す
+pub fn check(path: Option<&Path>) -> bool {
+    let Some(path) = path
+        .map(Path::to_path_buf)
+        .or_else(manager::get_theme_file_path)
+    else {
+        eprintln!("Could not determine project directories for theme file.");
+        return false;
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read theme file {:?}: {}", path, e);
+            return false;
+        }
+    };
+
+    let diagnostics = check_str(&content, &path);
+    let file = path.display();
+    for diagnostic in &diagnostics {
+        let source = diagnostic
+            .source
+            .clone()
+            .unwrap_or_else(|| file.to_string());
+        match diagnostic.location {
+            Some((line, col)) => print!("{}:{}:{}: ", source, line, col),
+            None => print!("{}: ", source),
+        }
+        println!("{}: {}", diagnostic.severity, diagnostic.message);
+        if !diagnostic.path.is_empty() {
+            println!("    at `{}`", diagnostic.path);
+        }
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    if diagnostics.is_empty() {
+        println!("{}: OK", file);
+    } else {
+        println!("{}: {} error(s), {} warning(s)", file, errors, warnings);
+    }
+    errors == 0
+}
+
+/// `path` から読み込んだテーマを検証し、見つかった問題をすべて返す。
+/// 形式は拡張子から判別し、`extends` は `path` を基準に解決する。
+/// 行・列は YAML と JSON の構文エラー、および YAML の各値について特定する
+pub fn check_str(content: &str, path: &Path) -> Vec<Diagnostic> {
+    let format = ThemeFormat::from_path(path);
+    // どの形式も一旦 serde_yaml::Value として読み込み、同じ検証を通す
+    let parsed = match format {
+        ThemeFormat::Yaml => serde_yaml::from_str::<Value>(content)
+            .map_err(|e| (e.to_string(), e.location().map(|l| (l.line(), l.column())))),
+        ThemeFormat::Toml => toml::from_str::<Value>(content).map_err(|e| {
+            let location = e.span().map(|span| offset_to_location(content, span.start));
+            (e.message().to_string(), location)
+        }),
+        ThemeFormat::Json => serde_json::from_str::<Value>(content)
+            .map_err(|e| (e.to_string(), Some((e.line(), e.column())))),
+    };
+    let value = match parsed {
+        Ok(value) => value,
+        Err((message, location)) => {
+            // 構文エラーの場合はそれ以上検証できない
+            return vec![Diagnostic {
+                severity: Severity::Error,
+                path: String::new(),
+                message,
+                location,
+                source: None,
+            }];
+        }
+    };
+
+    let mut diags = Diagnostics {
+        items: Vec::new(),
+        format,
+    };
+    let value = match migrate::migrate(value, format) {
+        Ok(migrated) => {
+            if migrated.changed {
+                diags.warning(
+                    "",
+                    format!(
+                        "theme uses an old layout (version {}); run `zsh-infinite theme migrate` to upgrade it",
+                        migrated.from.unwrap_or(0)
+                    ),
+                );
+            }
+            migrated.value
+        }
+        Err(e) => {
+            diags.error("version", e);
+            return diags.items;
+        }
+    };
+    let layers = layers(value.clone(), content, format, path);
+    // 継承元とマージした結果を検証する
+    match extends::resolve(value, format, path) {
+        Ok(mut value) => {
+            // パレットの参照を解決してから検証する。解決できなかった参照は一度だけ報告する
+            let unresolved = palette::resolve_all(&mut value, format);
+            check_theme(&value, "", &mut diags);
+            diags
+                .items
+                .retain(|d| !unresolved.iter().any(|u| u.path == d.path));
+            for u in unresolved {
+                diags.error(&u.path, u.message);
+            }
+        }
+        Err(e) => diags.error("extends", e),
+    }
+
+    let mut items = diags.items;
+    for item in &mut items {
+        locate(item, &layers);
+    }
+    items.sort_by(|a, b| {
+        (&a.source, a.location, a.severity).cmp(&(&b.source, b.location, b.severity))
+    });
+    items
+}
+
+/// `extends` で辿った 1 つのテーマ
+struct Layer {
+    /// 検証したファイルなら None、継承元ならそのパスかプリセット名
+    source: Option<String>,
+    /// 変換後、マージ前の値
+    value: Value,
+    /// YAML のファイルであれば値の位置
+    positions: Option<PositionIndex>,
+}
+
+/// 検証したファイルから `extends` を辿り、継承元を近い順に並べる。読めない継承元で止める
+fn layers(value: Value, content: &str, format: ThemeFormat, path: &Path) -> Vec<Layer> {
+    let mut layers = vec![Layer {
+        source: None,
+        value,
+        positions: (format == ThemeFormat::Yaml).then(|| PositionIndex::build(content)),
+    }];
+    let mut path = path.to_path_buf();
+    let mut visited = vec![path.clone()];
+    while let Some(Value::String(extends)) = layers.last().and_then(|l| l.value.get("extends")) {
+        let extends = extends.clone();
+        if let Some(theme) = PromptTheme::preset(&extends) {
+            if let Ok(value) = ThemeFormat::Yaml.to_value(&theme) {
+                layers.push(Layer {
+                    source: Some(format!("preset `{}`", extends)),
+                    value,
+                    positions: None,
+                });
+            }
+            break;
+        }
+        let base = extends::base_path(&extends, &path);
+        if visited.contains(&base) {
+            break;
+        }
+        visited.push(base.clone());
+        let Ok(content) = fs::read_to_string(&base) else {
+            break;
+        };
+        let format = ThemeFormat::from_path(&base);
+        let Some(value) = format
+            .parse(&content)
+            .ok()
+            .and_then(|value| migrate::migrate(value, format).ok())
+        else {
+            break;
+        };
+        layers.push(Layer {
+            source: Some(base.display().to_string()),
+            value: value.value,
+            positions: (format == ThemeFormat::Yaml).then(|| PositionIndex::build(&content)),
+        });
+        path = base;
+    }
+    layers
+}
+
+/// 問題の値が書かれたファイルと位置を探す。
+/// 検証したファイル、継承元の順に、その値を書いている最初のものを使う。
+/// パレットの参照から来た値は、参照先のパレットの色の位置で報告する
+fn locate(item: &mut Diagnostic, layers: &[Layer]) {
+    let reference = layers
+        .iter()
+        .find_map(|layer| lookup(&layer.value, &item.path))
+        .and_then(Value::as_str)
+        .and_then(|s| s.strip_prefix('$'))
+        .map(|s| s.split(':').next().unwrap_or(s));
+    if let Some(name) = reference
+        && !item.message.starts_with("unknown palette colour")
+    {
+        let entry = join(palette::PALETTE_KEY, name);
+        if layers
+            .iter()
+            .any(|layer| lookup(&layer.value, &entry).is_some())
+        {
+            item.message = format!("{} (used at `{}`)", item.message, item.path);
+            item.path = entry;
+        }
+    }
+    let Some(layer) = layers
+        .iter()
+        .find(|layer| lookup(&layer.value, &item.path).is_some())
+    else {
+        return;
+    };
+    item.source = layer.source.clone();
+    item.location = layer
+        .positions
+        .as_ref()
+        .and_then(|positions| positions.locate(&item.path));
+}
+
+/// ドット区切りの `path` の値を探す。enum はタグを飛ばして中身を辿る
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, lookup_segment)
+}
+
+fn lookup_segment<'a>(value: &'a Value, segment: &str) -> Option<&'a Value> {
+    match value {
+        Value::Tagged(tagged) => lookup_segment(&tagged.value, segment),
+        Value::Sequence(items) => items.get(segment.parse::<usize>().ok()?),
+        Value::Mapping(map) => map
+            .get(segment)
+            // `!perSegment` の区切りの番号
+            .or_else(|| map.get(Value::from(segment.parse::<u64>().ok()?)))
+            // TOML/JSON の `{Tag: 中身}`
+            .or_else(|| lookup_segment(enum_parts(value)?.1, segment)),
+        _ => None,
+    }
+}
+
+fn offset_to_location(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, col)
+}
+
+// --- 各構造体の検証 ---
+
+fn check_theme(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let Some(map) = expect_mapping(value, path, diags) else {
+        return;
+    };
+    let errors = diags.error_count();
+    check_required(map, path, &["prompt_contents_list"], diags);
+    for (key, child) in map {
+        let Some(key) = expect_key(key, path, diags) else {
+            continue;
+        };
+        let child_path = join(path, key);
+        match key {
+            "version" => check_leaf::<u32>(child, &child_path, diags),
+            "extends" => check_leaf::<String>(child, &child_path, diags),
+            "palette" => check_leaf::<BTreeMap<String, String>>(child, &child_path, diags),
+            "prompt_contents_list" => {
+                if child.as_sequence().is_some_and(|s| s.is_empty()) {
+                    diags.warning(&child_path, "no prompt lines defined");
+                }
+                check_seq(child, &child_path, diags, check_contents);
+            }
+            "accent_scope" => check_leaf::<AccentScope>(child, &child_path, diags),
+            "transient_color" | "transient_color_light" | "transient_color_dark" => {
+                check_color_scheme(child, &child_path, diags)
+            }
+            _ => unknown_key(&child_path, key, diags),
+        }
+    }
+    check_node::<PromptTheme>(value, path, errors, diags);
+}
+
+fn check_contents(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let Some(map) = expect_mapping(value, path, diags) else {
+        return;
+    };
+    let errors = diags.error_count();
+    check_required(
+        map,
+        path,
+        &[
+            "connection",
+            "left_segment_separators",
+            "right_segment_separators",
+            "accent_which",
+        ],
+        diags,
+    );
+    for (key, child) in map {
+        let Some(key) = expect_key(key, path, diags) else {
+            continue;
+        };
+        let child_path = join(path, key);
+        match key {
+            "left" | "right" => check_seq(child, &child_path, diags, check_content),
+            "color" | "color_light" | "color_dark" => check_color_scheme(child, &child_path, diags),
+            "connection" => check_leaf::<PromptConnection>(child, &child_path, diags),
+            "left_segment_separators" | "right_segment_separators" => {
+                check_separators(child, &child_path, diags)
+            }
+            "accent_which" => check_leaf::<AccentWhich>(child, &child_path, diags),
+            "min_contrast" => check_min_contrast(child, &child_path, diags),
+            _ => unknown_key(&child_path, key, diags),
+        }
+    }
+    check_node::<PromptContents>(value, path, errors, diags);
+}
+
+fn check_min_contrast(value: &Value, path: &str, diags: &mut Diagnostics) {
+    match value.as_f64() {
+        Some(ratio) if !CONTRAST_RANGE.contains(&(ratio as f32)) => diags.error(
+            path,
+            format!("contrast ratio {} is outside 1.0..=21.0", ratio),
+        ),
+        Some(ratio) if ratio > 7.0 => diags.warning(
+            path,
+            format!(
+                "contrast ratio {} is above 7 (WCAG AAA); most text will become black or white",
+                ratio
+            ),
+        ),
+        Some(_) => {}
+        None if value.is_null() => {}
+        None => diags.error(path, "expected a contrast ratio such as 4.5"),
+    }
+}
+
+fn check_separators(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let Some(map) = expect_mapping(value, path, diags) else {
+        return;
+    };
+    let errors = diags.error_count();
+    check_required(
+        map,
+        path,
+        &[
+            "start_separator",
+            "mid_separator",
+            "end_separator",
+            "edge_cap",
+            "bold_separation",
+        ],
+        diags,
+    );
+    for (key, child) in map {
+        let Some(key) = expect_key(key, path, diags) else {
+            continue;
+        };
+        let child_path = join(path, key);
+        match key {
+            "start_separator" | "mid_separator" | "end_separator" => {
+                check_leaf::<PromptSeparation>(child, &child_path, diags)
+            }
+            "edge_cap" | "bold_separation" => check_leaf::<bool>(child, &child_path, diags),
+            _ => unknown_key(&child_path, key, diags),
+        }
+    }
+    check_node::<PromptSegmentSeparators>(value, path, errors, diags);
+}
+
+fn check_color_scheme(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let Some(map) = expect_mapping(value, path, diags) else {
+        return;
+    };
+    let errors = diags.error_count();
+    check_required(
+        map,
+        path,
+        &["bg", "fg", "pc", "sc", "accent", "accent_which"],
+        diags,
+    );
+    for (key, child) in map {
+        let Some(key) = expect_key(key, path, diags) else {
+            continue;
+        };
+        let child_path = join(path, key);
+        match key {
+            "bg" | "fg" | "pc" | "sc" => check_color(child, &child_path, diags),
+            "accent" => check_accent(child, &child_path, diags),
+            "accent_which" => check_leaf::<AccentWhich>(child, &child_path, diags),
+            _ => unknown_key(&child_path, key, diags),
+        }
+    }
+    check_node::<PromptColorScheme>(value, path, errors, diags);
+}
+
+fn check_accent(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let errors = diags.error_count();
+    match enum_parts(value).as_ref().map(|(tag, inner)| (tag.as_str(), *inner)) {
+        Some(("single", inner)) => check_color(inner, path, diags),
+        Some(("rainbow", inner)) => check_rainbow(inner, path, diags),
+        Some(("gradient", inner)) => check_gradient(inner, path, diags),
+        Some(("cycle", inner)) => check_cycle(inner, path, diags),
+        Some(("perSegment", inner)) => check_per_segment(inner, path, diags),
+        Some((other, _)) => diags.error(
+            path,
+            format!(
+                "unknown accent type `!{}` (expected `!single`, `!rainbow`, `!gradient`, `!cycle` or `!perSegment`)",
+                other
+            ),
+        ),
+        None => diags.error(
+            path,
+            "expected an accent such as `!single Black`, `!rainbow FullColor(0,255,255)` or `!gradient [...]`",
+        ),
+    }
+    check_node::<AccentColor>(value, path, errors, diags);
+}
+
+fn check_rainbow(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let Some(map) = value.as_mapping() else {
+        check_color(value, path, diags);
+        return;
+    };
+    check_required(map, path, &["color"], diags);
+    for (key, child) in map {
+        let Some(key) = expect_key(key, path, diags) else {
+            continue;
+        };
+        let child_path = join(path, key);
+        match key {
+            "color" => check_color(child, &child_path, diags),
+            "span" => {
+                if child.as_f64().is_none() {
+                    diags.error(&child_path, "expected a hue span in degrees such as 60");
+                }
+            }
+            "direction" => check_leaf::<HueDirection>(child, &child_path, diags),
+            "saturation" | "lightness" => match child.as_f64() {
+                Some(v) if !(0.0..=1.0).contains(&v) => {
+                    diags.error(&child_path, format!("{} {} is outside 0.0..=1.0", key, v))
+                }
+                Some(_) => {}
+                None => diags.error(&child_path, "expected a number between 0.0 and 1.0"),
+            },
+            "offset" => check_leaf::<HueOffset>(child, &child_path, diags),
+            _ => unknown_key(&child_path, key, diags),
+        }
+    }
+}
+
+fn check_cycle(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let Some(colors) = value.as_sequence() else {
+        diags.error(path, "expected a list of colours");
+        return;
+    };
+    if colors.is_empty() {
+        diags.warning(path, "cycle has no colours; White will be used");
+    }
+    for (i, color) in colors.iter().enumerate() {
+        check_color(color, &join(path, &i.to_string()), diags);
+    }
+}
+
+fn check_per_segment(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let Some(map) = value.as_mapping() else {
+        diags.error(path, "expected a map from separator numbers to colours");
+        return;
+    };
+    if map.is_empty() {
+        diags.warning(path, "perSegment has no colours; White will be used");
+    }
+    for (index, color) in map {
+        let index = match index {
+            Value::Number(n) => n.to_string(),
+            Value::String(s) => s.clone(),
+            _ => String::new(),
+        };
+        let color_path = join(path, &index);
+        if index.trim().parse::<usize>().is_err() {
+            diags.error(
+                &color_path,
+                format!(
+                    "invalid separator number `{}` (expected 0, 1, 2, ...)",
+                    index
+                ),
+            );
+            continue;
+        }
+        check_color(color, &color_path, diags);
+    }
+}
+
+fn check_gradient(value: &Value, path: &str, diags: &mut Diagnostics) {
+    if let Some(map) = value.as_mapping() {
+        check_required(map, path, &["stops"], diags);
+        for (key, child) in map {
+            let Some(key) = expect_key(key, path, diags) else {
+                continue;
+            };
+            let child_path = join(path, key);
+            match key {
+                "interpolation" => check_leaf::<Interpolation>(child, &child_path, diags),
+                "stops" => check_gradient_stops(child, &child_path, diags),
+                _ => unknown_key(&child_path, key, diags),
+            }
+        }
+        return;
+    }
+    check_gradient_stops(value, path, diags);
+}
+
+fn check_gradient_stops(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let Some(stops) = value.as_sequence() else {
+        diags.error(path, "expected a list of 'COLOR:stop' strings");
+        return;
+    };
+    if stops.is_empty() {
+        diags.warning(path, "gradient has no stops; White will be used");
+    }
+    for (i, stop) in stops.iter().enumerate() {
+        let stop_path = join(path, &i.to_string());
+        let Some(s) = stop.as_str() else {
+            diags.error(&stop_path, "expected a 'COLOR:stop' string");
+            continue;
+        };
+        match parse_gradient_stop(s) {
+            Ok(stop) if !(0.0..=1.0).contains(&stop.pos) => diags.error(
+                &stop_path,
+                format!("gradient stop position {} is outside 0.0..=1.0", stop.pos),
+            ),
+            Ok(_) => {}
+            Err(e) => diags.error(&stop_path, e),
+        }
+    }
+}
+
+fn check_content(value: &Value, path: &str, diags: &mut Diagnostics) {
+    let errors = diags.error_count();
+    let Some((variant, body)) = enum_parts(value) else {
+        diags.error(path, "expected one of `!Literal`, `!BuildIn` or `!Shell`");
+        return;
+    };
+    let fields: &[&str] = match variant.as_str() {
+        "Literal" => &["value", "fg", "bg"],
+        "BuildIn" => &["command"],
+        "Shell" => &["cmd", "args", "envs", "fg", "bg"],
+        other => {
+            diags.error(
+                path,
+                format!(
+                    "unknown prompt content `{}` (expected `Literal`, `BuildIn` or `Shell`)",
+                    other
+                ),
+            );
+            return;
+        }
+    };
+    if let Some(map) = expect_mapping(body, path, diags) {
+        for (key, child) in map {
+            let Some(key) = expect_key(key, path, diags) else {
+                continue;
+            };
+            let child_path = join(path, key);
+            match key {
+                _ if !fields.contains(&key) => unknown_key(&child_path, key, diags),
+                "fg" | "bg" => check_optional_color(child, &child_path, diags),
+                "command" => check_leaf::<zsh_prompts::Commands>(child, &child_path, diags),
+                _ => {}
+            }
+        }
+    }
+    check_node::<PromptContent>(value, path, errors, diags);
+}
+
+// --- 値の検証ヘルパー ---
+
+fn check_color(value: &Value, path: &str, diags: &mut Diagnostics) {
+    match color_text(value) {
+        Some(s) => {
+            if let Err(e) = named_color_serde::deserialize_from_str(&s) {
+                diags.error(path, e);
+            }
+        }
+        None => diags.error(path, "expected a colour string"),
+    }
+}
+
+fn check_optional_color(value: &Value, path: &str, diags: &mut Diagnostics) {
+    match color_text(value) {
+        Some(s) => {
+            if let Err(e) = named_color_serde_option::deserialize_from_str(&s) {
+                diags.error(path, e);
+            }
+        }
+        None => diags.error(path, "expected a colour string or `None`"),
+    }
+}
+
+/// 色の欄の文字列。0〜255 の番号は数値で書かれていてもよい
+fn color_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => n.as_u64().map(|n| n.to_string()),
+        _ => None,
+    }
+}
+
+fn check_leaf<T: DeserializeOwned>(value: &Value, path: &str, diags: &mut Diagnostics) {
+    if let Err(e) = diags.format.from_value::<T>(value.clone()) {
+        diags.error(path, e);
+    }
+}
+
+/// 子要素からエラーが見つからなかった場合のみ、ノード全体をデシリアライズして
+/// 必須フィールドの欠落などを報告する
+fn check_node<T: DeserializeOwned>(
+    value: &Value,
+    path: &str,
+    errors_before: usize,
+    diags: &mut Diagnostics,
+) {
+    if diags.error_count() == errors_before
+        && let Err(e) = diags.format.from_value::<T>(value.clone())
+    {
+        diags.error(path, e);
+    }
+}
+
+fn check_seq(
+    value: &Value,
+    path: &str,
+    diags: &mut Diagnostics,
+    check_item: fn(&Value, &str, &mut Diagnostics),
+) {
+    match value.as_sequence() {
+        Some(items) => {
+            for (i, item) in items.iter().enumerate() {
+                check_item(item, &join(path, &i.to_string()), diags);
+            }
+        }
+        None => diags.error(path, "expected a list"),
+    }
+}
+
+fn expect_mapping<'a>(
+    value: &'a Value,
+    path: &str,
+    diags: &mut Diagnostics,
+) -> Option<&'a Mapping> {
+    let mapping = value.as_mapping();
+    if mapping.is_none() {
+        diags.error(path, "expected a mapping");
+    }
+    mapping
+}
+
+fn expect_key<'a>(key: &'a Value, path: &str, diags: &mut Diagnostics) -> Option<&'a str> {
+    let key_str = key.as_str();
+    if key_str.is_none() {
+        diags.error(path, format!("expected a string key, found {:?}", key));
+    }
+    key_str
+}
+
+fn check_required(map: &Mapping, path: &str, required: &[&str], diags: &mut Diagnostics) {
+    for field in required {
+        if !map.contains_key(*field) {
+            diags.error(path, format!("missing field `{}`", field));
+        }
+    }
+}
+
+fn unknown_key(path: &str, key: &str, diags: &mut Diagnostics) {
+    diags.warning(path, format!("unknown key `{}` is ignored", key));
+}
+
+/// `!Tag value` と `{Tag: value}` のどちらの形式の enum でも (タグ名, 中身) を返す
+fn enum_parts(value: &Value) -> Option<(String, &Value)> {
+    match value {
+        Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string();
+            Some((tag.trim_start_matches('!').to_string(), &tagged.value))
+        }
+        Value::Mapping(map) if map.len() == 1 => {
+            let (key, inner) = map.iter().next()?;
+            Some((key.as_str()?.to_string(), inner))
+        }
+        _ => None,
+    }
+}
+
+fn join(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
+
+// --- 行・列の特定 ---
+
+enum Frame {
+    Sequence { path: String, index: usize },
+    Mapping { path: String, key: Option<String> },
+}
+
+/// YAML のイベント列からパスごとの位置を記録する
+#[derive(Default)]
+struct PositionIndex {
+    positions: HashMap<String, (usize, usize)>,
+    stack: Vec<Frame>,
+}
+
+impl PositionIndex {
+    fn build(content: &str) -> Self {
+        let mut index = Self::default();
+        // serde_yaml で読めた以上、ここでの失敗は位置情報が欠けるだけで済ませる
+        let _ = Parser::new_from_str(content).load(&mut index, false);
+        index
+    }
+
+    fn locate(&self, path: &str) -> Option<(usize, usize)> {
+        let mut path = path;
+        loop {
+            if let Some(position) = self.positions.get(path) {
+                return Some(*position);
+            }
+            if path.is_empty() {
+                return None;
+            }
+            path = path
+                .rsplit_once('.')
+                .map(|(parent, _)| parent)
+                .unwrap_or("");
+        }
+    }
+
+    /// 次に来る値のパス。マッピングのキー待ちであれば None
+    fn value_path(&self) -> Option<String> {
+        match self.stack.last() {
+            None => Some(String::new()),
+            Some(Frame::Sequence { path, index }) => Some(join(path, &index.to_string())),
+            Some(Frame::Mapping {
+                path,
+                key: Some(key),
+            }) => Some(join(path, key)),
+            Some(Frame::Mapping { key: None, .. }) => None,
+        }
+    }
+
+    fn finish_value(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Sequence { index, .. }) => *index += 1,
+            Some(Frame::Mapping { key, .. }) => *key = None,
+            None => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for PositionIndex {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        let position = (mark.line(), mark.col() + 1);
+        match ev {
+            Event::Scalar(value, ..) => match self.value_path() {
+                Some(path) => {
+                    self.positions.insert(path, position);
+                    self.finish_value();
+                }
+                None => {
+                    if let Some(Frame::Mapping { path, key }) = self.stack.last_mut() {
+                        self.positions.insert(join(path, &value), position);
+                        *key = Some(value);
+                    }
+                }
+            },
+            Event::Alias(_) => self.finish_value(),
+            Event::SequenceStart(..) => {
+                let path = self.value_path().unwrap_or_default();
+                self.positions.entry(path.clone()).or_insert(position);
+                self.stack.push(Frame::Sequence { path, index: 0 });
+            }
+            Event::MappingStart(..) => {
+                let path = self.value_path().unwrap_or_default();
+                self.positions.entry(path.clone()).or_insert(position);
+                self.stack.push(Frame::Mapping { path, key: None });
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.stack.pop();
+                self.finish_value();
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::env;
+use zsh_seq::NamedColor;
+
+use super::{
+    color_parse,
+    gradient::{ANSI_PALETTE, code256_to_rgb, named_color_to_rgb, rgb_to_oklab},
+};
+
+/// 色数の自動判定を上書きする環境変数。`16`、`256`、`truecolor` のいずれか
+pub const COLOR_DEPTH_ENV: &str = "ZSH_INFINITE_COLORS";
+
+/// 端末が表示できる色数
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    /// 基本の 16 色
+    Ansi16,
+    /// 256 色
+    Ansi256,
+    /// 24 ビットカラー
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// 環境変数から色数を判定する
+    pub fn detect() -> Self {
+        Self::detect_with(|name| env::var(name).ok())
+    }
+
+    /// `var` で読んだ変数から色数を判定する。
+    /// `ZSH_INFINITE_COLORS`、`COLORTERM`、`TERM` の順に見る
+    pub fn detect_with(var: impl Fn(&str) -> Option<String>) -> Self {
+        if let Some(depth) = var(COLOR_DEPTH_ENV).as_deref().and_then(Self::parse) {
+            return depth;
+        }
+        if let Some(colorterm) = var("COLORTERM")
+            && matches!(
+                colorterm.to_ascii_lowercase().as_str(),
+                "truecolor" | "24bit"
+            )
+        {
+            return Self::TrueColor;
+        }
+        let Some(term) = var("TERM").filter(|term| !term.is_empty()) else {
+            // 端末の情報が無ければ色を変えない
+            return Self::TrueColor;
+        };
+        let term = term.to_ascii_lowercase();
+        if term.contains("direct")
+            || [
+                "kitty",
+                "alacritty",
+                "wezterm",
+                "foot",
+                "ghostty",
+                "contour",
+            ]
+            .iter()
+            .any(|name| term.contains(name))
+        {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else if term == "linux"
+            || term == "dumb"
+            || term == "ansi"
+            || term.starts_with("vt")
+            || term.starts_with("screen")
+            || term.starts_with("tmux")
+            || term.starts_with("cons")
+            || term.ends_with("-16color")
+            || term.ends_with("-color")
+        {
+            Self::Ansi16
+        } else {
+            Self::Ansi256
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "16" | "8" | "ansi" => Some(Self::Ansi16),
+            "256" => Some(Self::Ansi256),
+            "truecolor" | "24bit" | "full" => Some(Self::TrueColor),
+            _ => None,
+        }
+    }
+
+    /// この色数で表示できる、最も近い色にする
+    pub fn quantize(self, color: NamedColor) -> NamedColor {
+        match (self, color) {
+            (Self::TrueColor, color) => color,
+            (Self::Ansi256, NamedColor::FullColor(rgb)) => NamedColor::Code256(nearest_256(rgb)),
+            (Self::Ansi16, NamedColor::FullColor(_) | NamedColor::Code256(16..)) => {
+                NAMED_16[nearest(named_color_to_rgb(&color), ANSI_PALETTE.iter().copied())]
+            }
+            (_, color) => color,
+        }
+    }
+
+    /// zsh のプロンプト文字列の `%F{...}` と `%K{...}` の色を、この色数で表示できる色にする
+    pub fn quantize_prompt(self, prompt: &str) -> String {
+        if self == Self::TrueColor {
+            return prompt.to_string();
+        }
+        let mut out = String::with_capacity(prompt.len());
+        let mut rest = prompt;
+        while let Some(i) = rest.find('%') {
+            out.push_str(&rest[..i]);
+            rest = &rest[i..];
+            // `%%` は文字の % なので、その後ろを色として読まない
+            if let Some(after) = rest.strip_prefix("%%") {
+                out.push_str("%%");
+                rest = after;
+                continue;
+            }
+            let arg = rest
+                .get(1..3)
+                .filter(|kind| *kind == "F{" || *kind == "K{")
+                .and_then(|_| rest[3..].find('}').map(|end| &rest[3..3 + end]));
+            match arg.map(|arg| (arg, color_parse::parse(arg))) {
+                Some((arg, Ok(color))) => {
+                    out.push_str(&rest[..3]);
+                    out.push_str(&zsh_color(self.quantize(color)));
+                    out.push('}');
+                    rest = &rest[3 + arg.len() + 1..];
+                }
+                _ => {
+                    out.push('%');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// 基本の 16 色を色番号の順に並べたもの
+pub const NAMED_16: [NamedColor; 16] = [
+    NamedColor::Black,
+    NamedColor::Red,
+    NamedColor::Green,
+    NamedColor::Yellow,
+    NamedColor::Blue,
+    NamedColor::Magenta,
+    NamedColor::Cyan,
+    NamedColor::White,
+    NamedColor::LightBlack,
+    NamedColor::LightRed,
+    NamedColor::LightGreen,
+    NamedColor::LightYellow,
+    NamedColor::LightBlue,
+    NamedColor::LightMagenta,
+    NamedColor::LightCyan,
+    NamedColor::LightWhite,
+];
+
+/// 256 色のうち最も近い色の番号。
+/// 0〜15 は端末ごとに色が違うため、6×6×6 の色と 24 段階の灰色から選ぶ
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    16 + nearest(rgb, (16..=255).map(code256_to_rgb)) as u8
+}
+
+/// `candidates` のうち、OKLab で `rgb` に最も近い色の位置
+fn nearest(rgb: (u8, u8, u8), candidates: impl Iterator<Item = (u8, u8, u8)>) -> usize {
+    let (l, a, b) = rgb_to_oklab(rgb);
+    candidates
+        .map(|candidate| {
+            let (cl, ca, cb) = rgb_to_oklab(candidate);
+            (l - cl).powi(2) + (a - ca).powi(2) + (b - cb).powi(2)
+        })
+        .enumerate()
+        .min_by(|(_, x), (_, y)| x.total_cmp(y))
+        .map_or(0, |(i, _)| i)
+}
+
+/// `%F{...}` に書く色。基本の 16 色と 256 色は番号で書く
+fn zsh_color(color: NamedColor) -> String {
+    match color {
+        NamedColor::Code256(code) => code.to_string(),
+        NamedColor::FullColor((r, g, b)) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        named => {
+            let rgb = named_color_to_rgb(&named);
+            ANSI_PALETTE
+                .iter()
+                .position(|c| *c == rgb)
+                .unwrap_or_default()
+                .to_string()
+        }
+    }
+}
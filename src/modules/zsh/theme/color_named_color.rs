@@ -1,3 +1,7 @@
+use zsh_seq::NamedColor;
+
+use super::gradient::named_color_to_rgb;
+
 pub trait ToNamedColor {
     fn to_named_color(&self) -> zsh_seq::NamedColor;
 }
@@ -17,3 +21,40 @@ impl ToNamedColor for zsh_prompts::Color {
         }
     }
 }
+
+/// 組み込みコマンドの `color` に渡す文字列。基本の 8 色は名前、それ以外は `#RRGGBB`
+pub fn color_string(color: NamedColor) -> String {
+    match color {
+        NamedColor::Black => "black".to_string(),
+        NamedColor::Red => "red".to_string(),
+        NamedColor::Green => "green".to_string(),
+        NamedColor::Yellow => "yellow".to_string(),
+        NamedColor::Blue => "blue".to_string(),
+        NamedColor::Magenta => "magenta".to_string(),
+        NamedColor::Cyan => "cyan".to_string(),
+        NamedColor::White => "white".to_string(),
+        other => {
+            let (r, g, b) = named_color_to_rgb(&other);
+            format!("#{:02X}{:02X}{:02X}", r, g, b)
+        }
+    }
+}
+
+/// `NamedColor` を zsh_prompts の色にする。基本の 8 色以外は RGB になる
+pub fn prompts_color(color: NamedColor) -> zsh_prompts::Color {
+    use zsh_prompts::Color;
+    match color {
+        NamedColor::Black => Color::Black,
+        NamedColor::Red => Color::Red,
+        NamedColor::Green => Color::Green,
+        NamedColor::Yellow => Color::Yellow,
+        NamedColor::Blue => Color::Blue,
+        NamedColor::Magenta => Color::Magenta,
+        NamedColor::Cyan => Color::Cyan,
+        NamedColor::White => Color::White,
+        other => {
+            let (r, g, b) = named_color_to_rgb(&other);
+            Color::Rgb(r, g, b)
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use zsh_seq::NamedColor;
+
+use super::gradient::hsl_to_rgb;
+
+/// 端末の 16 色の名前。`light-`/`bright-` を付けると明るい色になる
+const TERMINAL_COLORS: [(&str, NamedColor, NamedColor); 8] = [
+    ("black", NamedColor::Black, NamedColor::LightBlack),
+    ("red", NamedColor::Red, NamedColor::LightRed),
+    ("green", NamedColor::Green, NamedColor::LightGreen),
+    ("yellow", NamedColor::Yellow, NamedColor::LightYellow),
+    ("blue", NamedColor::Blue, NamedColor::LightBlue),
+    ("magenta", NamedColor::Magenta, NamedColor::LightMagenta),
+    ("cyan", NamedColor::Cyan, NamedColor::LightCyan),
+    ("white", NamedColor::White, NamedColor::LightWhite),
+];
+
+/// CSS の色名。端末の 16 色と同じ名前 (`red`、`lightgreen`、`gray` など) は端末の色を使うので含めない
+const CSS_COLORS: [(&str, u32); 133] = [
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgray", 0xA9A9A9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xA9A9A9),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("greenyellow", 0xADFF2F),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightcoral", 0xF08080),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightgray", 0xD3D3D3),
+    ("lightgrey", 0xD3D3D3),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("rebeccapurple", 0x663399),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellowgreen", 0x9ACD32),
+];
+
+/// テーマ全体で使う色の書き方を読む。大文字と小文字は区別しない
+///
+/// - 端末の 16 色: `Red`、`LightRed`、`light-red`、`bright-red` など
+/// - `Code256(n)` または 0〜255 の番号
+/// - `FullColor(r,g,b)`、`#RGB`、`#RRGGBB`、`rgb(r, g, b)`、`hsl(h, s%, l%)`
+/// - CSS の色名 (`orange`、`rebeccapurple` など)
+pub fn parse(s: &str) -> Result<NamedColor, String> {
+    let lower = s.trim().to_ascii_lowercase();
+    parse_lower(&lower).ok_or_else(|| {
+        format!(
+            "invalid colour `{}` (expected a colour name, #RRGGBB, rgb(), hsl(), Code256(n), FullColor(r,g,b) or 0-255)",
+            s.trim()
+        )
+    })
+}
+
+fn parse_lower(s: &str) -> Option<NamedColor> {
+    if let Some(hex) = s.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+    if let Ok(code) = s.parse::<u8>() {
+        return Some(NamedColor::Code256(code));
+    }
+    if let Some(args) = function_args(s, "code256") {
+        return match args.as_slice() {
+            [code] => code.parse().ok().map(NamedColor::Code256),
+            _ => None,
+        };
+    }
+    if let Some(args) = function_args(s, "fullcolor").or_else(|| function_args(s, "rgb")) {
+        return match args.as_slice() {
+            [r, g, b] => Some(NamedColor::FullColor((
+                channel(r)?,
+                channel(g)?,
+                channel(b)?,
+            ))),
+            _ => None,
+        };
+    }
+    if let Some(args) = function_args(s, "hsl") {
+        return match args.as_slice() {
+            [h, sat, l] => {
+                let h = h
+                    .strip_suffix("deg")
+                    .unwrap_or(h)
+                    .trim()
+                    .parse::<f32>()
+                    .ok()?;
+                let (r, g, b) = hsl_to_rgb(h.rem_euclid(360.0), percent(sat)?, percent(l)?);
+                Some(NamedColor::FullColor((r, g, b)))
+            }
+            _ => None,
+        };
+    }
+    terminal_color(s).or_else(|| {
+        CSS_COLORS
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, rgb)| {
+                NamedColor::FullColor(((rgb >> 16) as u8, (rgb >> 8) as u8, *rgb as u8))
+            })
+    })
+}
+
+/// 端末の 16 色の名前。`grey` と `purple` は端末の色の別名として扱う
+fn terminal_color(s: &str) -> Option<NamedColor> {
+    let s = match s {
+        "grey" | "gray" => "light-black",
+        "purple" => "magenta",
+        "bright-purple" | "brightpurple" | "light-purple" => "light-magenta",
+        s => s,
+    };
+    let (light, name) = match ["light-", "light", "bright-", "bright"]
+        .iter()
+        .find_map(|prefix| s.strip_prefix(prefix))
+    {
+        Some(name) => (true, name),
+        None => (false, s),
+    };
+    TERMINAL_COLORS
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, normal, bright)| if light { *bright } else { *normal })
+}
+
+fn parse_hex(hex: &str) -> Option<NamedColor> {
+    if !hex.is_ascii() {
+        return None;
+    }
+    let digit = |i: usize, len: usize| u8::from_str_radix(&hex[i..i + len], 16).ok();
+    match hex.len() {
+        3 => Some(NamedColor::FullColor((
+            digit(0, 1)? * 0x11,
+            digit(1, 1)? * 0x11,
+            digit(2, 1)? * 0x11,
+        ))),
+        6 => Some(NamedColor::FullColor((
+            digit(0, 2)?,
+            digit(2, 2)?,
+            digit(4, 2)?,
+        ))),
+        _ => None,
+    }
+}
+
+/// `name(a, b, c)` の引数。区切りはカンマでも空白でもよい
+fn function_args<'a>(s: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let args = s
+        .strip_prefix(name)?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')?;
+    Some(
+        args.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|arg| !arg.is_empty())
+            .collect(),
+    )
+}
+
+/// `rgb()` の各成分。0〜255 か百分率
+fn channel(s: &str) -> Option<u8> {
+    match s.strip_suffix('%') {
+        Some(p) => {
+            let p = p.parse::<f32>().ok()?;
+            (0.0..=100.0)
+                .contains(&p)
+                .then(|| (p / 100.0 * 255.0).round() as u8)
+        }
+        None => s.parse().ok(),
+    }
+}
+
+/// `hsl()` の彩度と明度。`%` は省略できる
+fn percent(s: &str) -> Option<f32> {
+    let p = s.strip_suffix('%').unwrap_or(s).parse::<f32>().ok()?;
+    (0.0..=100.0).contains(&p).then_some(p / 100.0)
+}
@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
 use zsh_seq::NamedColor;
 
-use super::gradient::{GradientPart, deserialize_gradient, serialize_gradient};
+use super::gradient::{Gradient, deserialize_gradient, serialize_gradient};
 use super::named_color_serde;
-use super::prompt_theme::AccentWhich; // Import AccentWhich from prompt_theme.rs
+use super::per_segment::PerSegment;
+use super::prompt_theme::AccentWhich;
+use super::rainbow::{DynamicHues, Rainbow, deserialize_rainbow, serialize_rainbow};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PromptColorScheme {
@@ -34,60 +36,45 @@ impl PromptColorScheme {
 #[serde(rename_all = "camelCase")]
 pub enum AccentColor {
     Single(#[serde(with = "named_color_serde")] NamedColor),
-    Rainbow(#[serde(with = "named_color_serde")] NamedColor),
+    #[serde(
+        serialize_with = "serialize_rainbow",
+        deserialize_with = "deserialize_rainbow"
+    )]
+    Rainbow(Rainbow),
     #[serde(
         serialize_with = "serialize_gradient",
         deserialize_with = "deserialize_gradient"
     )]
-    Gradient(GradientPart),
+    Gradient(Gradient),
+    /// 区切りごとに順に使い、最後まで来たら最初に戻る色の列
+    Cycle(#[serde(with = "named_color_serde::list")] Vec<NamedColor>),
+    /// 区切りの番号ごとに決めた色
+    PerSegment(PerSegment),
 }
 
 impl AccentColor {
-    pub fn get(&self, progress: f32) -> NamedColor {
+    /// `progress` (0.0〜1.0) の位置の色。虹の動的なずれは `hues` から取る。
+    /// `Cycle` と `PerSegment` は位置を色の列や番号の範囲に当てはめて取る
+    pub fn get(&self, progress: f32, hues: &DynamicHues) -> NamedColor {
         match self {
             Self::Single(color) => color.to_owned(),
-            Self::Rainbow(base_color) => {
-                let (r, g, b) = match base_color {
-                    NamedColor::FullColor(rgb) => *rgb,
-                    _ => (255, 0, 0), // デフォルト赤
-                };
-                let (start_hue, saturation, lightness) = super::gradient::rgb_to_hsl(r, g, b);
-                let hue = (start_hue + progress * 360.0) % 360.0;
-                let rgb = super::gradient::hsl_to_rgb(hue, saturation, lightness);
-                NamedColor::FullColor(rgb)
-            }
-            Self::Gradient(stops) => {
-                if stops.is_empty() {
-                    return NamedColor::White;
-                }
-                if stops.len() == 1 {
-                    return NamedColor::FullColor(stops[0].0);
-                }
-
-                let mut sorted_stops = stops.clone();
-                sorted_stops.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-
-                let first = &sorted_stops[0];
-                if progress <= first.1 {
-                    return NamedColor::FullColor(first.0);
-                }
-                let last = &sorted_stops[sorted_stops.len() - 1];
-                if progress >= last.1 {
-                    return NamedColor::FullColor(last.0);
-                }
+            Self::Rainbow(rainbow) => rainbow.get(progress, hues),
+            Self::Gradient(gradient) => gradient.get(progress),
+            Self::Cycle(colors) => match colors.len() {
+                0 => NamedColor::White,
+                len => colors[((progress.clamp(0.0, 1.0) * len as f32) as usize).min(len - 1)],
+            },
+            Self::PerSegment(per_segment) => per_segment.get_progress(progress),
+        }
+    }
 
-                for i in 0..sorted_stops.len() - 1 {
-                    let (c1_rgb, p1) = &sorted_stops[i];
-                    let (c2_rgb, p2) = &sorted_stops[i + 1];
-                    if progress >= *p1 && progress <= *p2 {
-                        let t = (progress - p1) / (p2 - p1);
-                        return NamedColor::FullColor(super::gradient::lerp_rgb_color(
-                            *c1_rgb, *c2_rgb, t,
-                        ));
-                    }
-                }
-                NamedColor::FullColor(stops[0].0)
-            }
+    /// `index` 番目の区切りの色。`progress` はその区切りの位置 (0.0〜1.0)。
+    /// `Cycle` と `PerSegment` は番号で、それ以外は位置で色を決める
+    pub fn at(&self, index: usize, progress: f32, hues: &DynamicHues) -> NamedColor {
+        match self {
+            Self::Cycle(colors) if !colors.is_empty() => colors[index % colors.len()],
+            Self::PerSegment(per_segment) => per_segment.get(index),
+            _ => self.get(progress, hues),
         }
     }
 }
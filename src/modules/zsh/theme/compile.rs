@@ -0,0 +1,547 @@
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use unicode_width::UnicodeWidthStr;
+use zsh_prompts::{Color, Commands, git::GitStatusOptions};
+use zsh_seq::{NamedColor, ZshPromptBuilder, ZshSequence};
+
+use super::{
+    appearance::Appearance,
+    color_named_color::ToNamedColor,
+    color_parse, manager,
+    prompt_theme::{AccentScope, PromptContent, PromptContents, PromptTheme},
+    rainbow::DynamicHues,
+};
+use crate::zsh::prompt::{Prompt, PromptConnection, PromptCurveLine};
+
+/// 空になり得るセグメントの表示の組み合わせを全て書き出す、片側あたりの上限。
+/// 超えた場合は空でも常に表示する
+const MAX_OPTIONAL_SEGMENTS: usize = 6;
+/// 実行時に zsh の変数で置き換える位置の印。`\x01変数名\x01` の形で埋め込む
+const MARK: char = '\u{1}';
+
+/// 生成したスクリプトを読み込んだときに最初に実行する部分
+const HEADER: &str = r#"# Generated by `zsh-infinite theme compile`. Source this file from .zshrc;
+# it needs neither the zsh-infinite binary nor the zsh module.
+
+zmodload zsh/datetime
+# Full colours are shown as the nearest 256 colour unless the terminal supports them
+[[ ${ZSH_INFINITE_COLORS:-$COLORTERM} == (truecolor|24bit) ]] || zmodload -s zsh/nearcolor
+autoload -Uz add-zsh-hook
+ZLE_RPROMPT_INDENT=0
+
+typeset -gi _zsh_infinite_status=0
+typeset -gF _zsh_infinite_elapsed=0
+typeset -g _zsh_infinite_start=
+
+# Sets REPLY to the connection that stretches a line to the terminal width
+# $1: left side, $2: right side, $3: width of one frame edge,
+# $4: connection character, $5: its width
+function _zsh_infinite_fill() {
+    local zero='%([BSUbfksu]|([FK]|){*})' conn=$4
+    local -i n=$(( COLUMNS * 2 - ${#${(S%%)1//$~zero/}} - ${#${(S%%)2//$~zero/}} - $3 * 2 ))
+    (( n < 0 )) && n=0
+    n=$(( n % COLUMNS / $5 ))
+    REPLY=${(pl:n::$conn:)}
+}
+"#;
+
+/// OS のアイコン。`$OSTYPE` で判定する
+const OS_BODY: &str = r#"case $OSTYPE in
+    (linux-android*) REPLY=$'\uf17b' ;;
+    (linux*) REPLY=$'\uf17c' ;;
+    (darwin*) REPLY=$'\uf179' ;;
+    (freebsd*|openbsd*|netbsd*) REPLY=$'\uf30c' ;;
+    (*) REPLY=$OSTYPE ;;
+esac"#;
+
+/// 変換結果と、zsh で再現できなかったものの一覧
+pub struct Compiled {
+    pub script: String,
+    pub stubbed: Vec<String>,
+}
+
+/// 1 つのセグメントの変換結果
+struct Part {
+    seqs: Vec<ZshSequence>,
+    /// 空になり得るセグメントの出力を入れる変数
+    optional: Option<String>,
+}
+
+/// `vcs_info` の設定
+struct VcsInfo {
+    formats: String,
+    actionformats: String,
+    stagedstr: String,
+    unstagedstr: String,
+}
+
+#[derive(Default)]
+struct Compiler {
+    /// セグメントの出力を `REPLY` に入れる関数の本体。`functions[n]` の結果は `seg[n + 1]` に入る
+    functions: Vec<String>,
+    vcs_info: Option<VcsInfo>,
+    stubbed: Vec<String>,
+}
+
+fn mark(name: &str) -> String {
+    format!("{MARK}{name}{MARK}")
+}
+
+/// zsh の単一引用符で囲む
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// 二重引用符で囲む。Shell の引数と同じく `$VAR` だけは展開させる
+fn double_quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('`', "\\`")
+        .replace("$(", "\\$(");
+    format!("\"{}\"", escaped)
+}
+
+/// 印を含むプロンプト文字列を、印の位置で変数を展開する zsh の文字列にする
+fn template(prompt: &str) -> String {
+    let mut out = String::new();
+    for (i, part) in prompt.split(MARK).enumerate() {
+        if i % 2 == 1 {
+            let _ = write!(out, "\"${{{}}}\"", part);
+        } else if !part.is_empty() {
+            out.push_str(&quote(part));
+        }
+    }
+    if out.is_empty() {
+        "''".to_string()
+    } else {
+        out
+    }
+}
+
+/// プロンプトのエスケープを含む `raw` を色で囲む
+fn colored(color: Option<NamedColor>, raw: &str) -> String {
+    let Some(color) = color else {
+        return raw.to_string();
+    };
+    let placeholder = mark("");
+    ZshPromptBuilder::new()
+        .color(color)
+        .str(&placeholder)
+        .end_color()
+        .build()
+        .replace(&placeholder, raw)
+}
+
+/// セグメントの文字列を前景色と背景色で囲む
+pub(super) fn wrap(
+    fg: Option<NamedColor>,
+    bg: Option<NamedColor>,
+    value: String,
+) -> Vec<ZshSequence> {
+    let mut seqs = Vec::new();
+    if let Some(c) = bg {
+        seqs.push(ZshSequence::BackgroundColor(c));
+    }
+    if let Some(c) = fg {
+        seqs.push(ZshSequence::ForegroundColor(c));
+    }
+    seqs.push(ZshSequence::Literal(value));
+    if fg.is_some() {
+        seqs.push(ZshSequence::ForegroundColorEnd);
+    }
+    if bg.is_some() {
+        seqs.push(ZshSequence::BackgroundColorEnd);
+    }
+    seqs
+}
+
+/// テーマを zsh だけで動くプロンプトのスクリプトにする
+pub fn compile(theme: &PromptTheme) -> Compiled {
+    let mut compiler = Compiler::default();
+    if theme.has_variant(Appearance::Light) || theme.has_variant(Appearance::Dark) {
+        compiler
+            .stubbed
+            .push("light/dark colour variants (`color` is always used)".to_string());
+    }
+    if theme.accent_scope != AccentScope::Line {
+        compiler
+            .stubbed
+            .push("accent_scope (each line restarts its accent)".to_string());
+    }
+    let lines: Vec<(Vec<Part>, Vec<Part>)> = theme
+        .prompt_contents_list
+        .iter()
+        .map(|contents| {
+            let left = contents.left.iter().map(|c| compiler.content(c)).collect();
+            let right = contents.right.iter().map(|c| compiler.content(c)).collect();
+            (left, right)
+        })
+        .collect();
+
+    let mut script = HEADER.to_string();
+    if let Some(vcs) = &compiler.vcs_info {
+        script.push_str("\nautoload -Uz vcs_info\n");
+        script.push_str("zstyle ':vcs_info:*' enable git\n");
+        script.push_str("zstyle ':vcs_info:*' check-for-changes true\n");
+        for (key, value) in [
+            ("formats", &vcs.formats),
+            ("actionformats", &vcs.actionformats),
+            ("stagedstr", &vcs.stagedstr),
+            ("unstagedstr", &vcs.unstagedstr),
+        ] {
+            let _ = writeln!(script, "zstyle ':vcs_info:git:*' {} {}", key, quote(value));
+        }
+    }
+    for (i, body) in compiler.functions.iter().enumerate() {
+        let _ = writeln!(
+            script,
+            "\nfunction _zsh_infinite_seg_{}() {{\n{}\n}}",
+            i + 1,
+            indent(body, 1)
+        );
+    }
+
+    script.push_str("\nfunction _zsh_infinite_precmd() {\n");
+    script.push_str(
+        r#"    _zsh_infinite_status=$?
+    if [[ -n $_zsh_infinite_start ]]; then
+        _zsh_infinite_elapsed=$(( EPOCHREALTIME - _zsh_infinite_start ))
+        _zsh_infinite_start=
+    else
+        _zsh_infinite_elapsed=0
+    fi
+    print -n '\e[0 q'
+    local REPLY left right lines
+    local -a seg
+"#,
+    );
+    if compiler.vcs_info.is_some() {
+        script.push_str("    vcs_info\n");
+    }
+    for i in 1..=compiler.functions.len() {
+        let _ = writeln!(script, "    _zsh_infinite_seg_{}; seg[{}]=$REPLY", i, i);
+    }
+    for (i, (contents, (left, right))) in theme.prompt_contents_list.iter().zip(&lines).enumerate()
+    {
+        let _ = writeln!(script, "    # line {}", i + 1);
+        script.push_str(&compiler.side(i, contents, left, false));
+        script.push_str(&compiler.side(i, contents, right, true));
+        script.push_str(&line(i, contents));
+    }
+
+    let (sc, connection) = match theme.prompt_contents_list.last() {
+        Some(contents) => (contents.color.sc, contents.connection),
+        None => (NamedColor::LightBlack, PromptConnection::default()),
+    };
+    let curved_lines = PromptCurveLine::from(connection);
+    let h = &curved_lines.horizontal;
+    let end = ZshPromptBuilder::new()
+        .color(sc)
+        .str(&curved_lines.bottom_left)
+        .str(h)
+        .str(" ")
+        .end_color();
+    let rprompt = ZshPromptBuilder::new()
+        .color(sc)
+        .str(h)
+        .str(&curved_lines.bottom_right)
+        .end_color();
+    let _ = writeln!(script, "    PROMPT=\"$lines\"{}", template(&end.build()));
+    let _ = writeln!(script, "    RPROMPT={}", template(&rprompt.build()));
+    script.push_str(
+        r#"    # Segment output is shown as is, so it must not be expanded again
+    if [[ -o PROMPT_SUBST ]]; then
+        PROMPT=${${${PROMPT//\\/\\\\}//\$/\\\$}//\`/\\\`}
+        RPROMPT=${${${RPROMPT//\\/\\\\}//\$/\\\$}//\`/\\\`}
+    fi
+"#,
+    );
+    script.push_str("}\n");
+
+    let transient = |color: NamedColor| {
+        template(
+            &ZshPromptBuilder::new()
+                .color(color)
+                .str("❯ ")
+                .end_color()
+                .reset_styles()
+                .build(),
+        )
+    };
+    let _ = write!(
+        script,
+        r#"
+function _zsh_infinite_line_finish() {{
+    _zsh_infinite_start=$EPOCHREALTIME
+    if (( _zsh_infinite_status == 0 )); then
+        PROMPT={}
+    else
+        PROMPT={}
+    fi
+    RPROMPT=
+    zle reset-prompt
+}}
+
+add-zsh-hook precmd _zsh_infinite_precmd
+zle -N zle-line-finish _zsh_infinite_line_finish
+"#,
+        transient(theme.transient_color.pc),
+        transient(theme.transient_color.sc)
+    );
+
+    Compiled {
+        script,
+        stubbed: compiler.stubbed,
+    }
+}
+
+/// 左右の内容と繋ぎで 1 行を組み立て、`lines` に足すコード
+fn line(i: usize, contents: &PromptContents) -> String {
+    let curved_lines = PromptCurveLine::from(contents.connection);
+    let h = &curved_lines.horizontal;
+    let connection = contents.connection.to_string();
+    let conn_width = UnicodeWidthStr::width(connection.as_str()).max(1);
+    let side_width = UnicodeWidthStr::width(curved_lines.top_left.as_str()) + conn_width;
+    let (start, end) = if i == 0 {
+        (&curved_lines.top_left, &curved_lines.top_right)
+    } else {
+        (&curved_lines.cross_left, &curved_lines.cross_right)
+    };
+    let row = ZshPromptBuilder::new()
+        .color(contents.color.sc)
+        .str(start)
+        .str(h)
+        .end_color()
+        .str(&mark("left"))
+        .color(contents.color.pc)
+        .str(&mark("REPLY"))
+        .end_color()
+        .str(&mark("right"))
+        .color(contents.color.sc)
+        .str(h)
+        .str(end)
+        .end_color();
+    format!(
+        "    _zsh_infinite_fill \"$left\" \"$right\" {} {} {}\n    lines+={}$'\\n'\n",
+        side_width,
+        quote(&connection),
+        conn_width,
+        template(&row.build())
+    )
+}
+
+fn indent(body: &str, level: usize) -> String {
+    body.lines()
+        .map(|line| format!("{}{}", "    ".repeat(level), line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Compiler {
+    /// セグメントの出力を作る関数を追加し、その出力を入れる変数名を返す
+    fn function(&mut self, body: String) -> String {
+        self.functions.push(body);
+        format!("seg[{}]", self.functions.len())
+    }
+
+    fn color(&mut self, color: Option<&str>) -> Option<NamedColor> {
+        let color = color?;
+        let parsed = color_parse::parse(color).ok();
+        if parsed.is_none() {
+            self.stubbed.push(format!("color {:?}", color));
+        }
+        parsed
+    }
+
+    fn content(&mut self, content: &PromptContent) -> Part {
+        match content {
+            PromptContent::Literal { value, fg, bg } => Part {
+                seqs: wrap(*fg, *bg, value.clone()),
+                optional: None,
+            },
+            PromptContent::Shell {
+                cmd,
+                args,
+                envs,
+                fg,
+                bg,
+            } => {
+                let mut envs: Vec<_> = envs.iter().collect();
+                envs.sort();
+                let command: Vec<String> = envs
+                    .into_iter()
+                    .map(|(key, value)| format!("{}={}", key, quote(value)))
+                    .chain(std::iter::once(quote(cmd)))
+                    .chain(args.iter().map(|arg| double_quote(arg)))
+                    .collect();
+                let body = format!(
+                    "REPLY=$({} 2>/dev/null) || REPLY=\nREPLY=${{${{REPLY//\\%/%%}}//$'\\n'/ }}",
+                    command.join(" ")
+                );
+                let var = self.function(body);
+                Part {
+                    seqs: wrap(*fg, *bg, mark(&var)),
+                    optional: Some(var),
+                }
+            }
+            PromptContent::BuildIn { command } => self.build_in(command),
+        }
+    }
+
+    fn build_in(&mut self, command: &Commands) -> Part {
+        let (body, color, optional) = match command {
+            Commands::Pwd { color } => ("REPLY='%~'".to_string(), color, false),
+            Commands::Time { color } => ("REPLY='%D{%H:%M:%S}'".to_string(), color, false),
+            Commands::Os { color } => (OS_BODY.to_string(), color, false),
+            Commands::Cmd {
+                last_command_executed,
+                color,
+                ..
+            } => {
+                let mut body =
+                    r#"(( _zsh_infinite_status )) && REPLY="✘ $_zsh_infinite_status" || REPLY='✔'"#
+                        .to_string();
+                if last_command_executed.is_some() {
+                    body.push_str(
+                        "\n(( _zsh_infinite_elapsed >= 1 )) && REPLY+=\" ${_zsh_infinite_elapsed%.*}s\"",
+                    );
+                }
+                (body, color, false)
+            }
+            Commands::Git { path, options } => {
+                self.git(path.as_deref(), options);
+                ("REPLY=$vcs_info_msg_0_".to_string(), &None, true)
+            }
+        };
+        let color = self.color(color.as_deref());
+        let var = self.function(body);
+        Part {
+            seqs: wrap(color, None, mark(&var)),
+            optional: optional.then_some(var),
+        }
+    }
+
+    /// Git の色を `vcs_info` の書式にする
+    fn git(&mut self, path: Option<&str>, options: &GitStatusOptions) {
+        if path.is_some() {
+            self.stubbed
+                .push("Git `path` (the repository of the current directory is shown)".to_string());
+        }
+        for (name, color) in [
+            ("untracked", options.untracked_color_option),
+            ("stashed", options.stashed_color_option),
+            ("clean", options.clean_color_option),
+            ("ahead", options.ahead_color_option),
+            ("behind", options.behind_color_option),
+        ] {
+            if color.is_some() {
+                self.stubbed
+                    .push(format!("Git {} status (vcs_info has no equivalent)", name));
+            }
+        }
+        let color = |color: Option<Color>| {
+            color
+                .or(options.default_color_option)
+                .map(|c| c.to_named_color())
+        };
+        let branch = format!(
+            "{} {}",
+            colored(color(options.git_icon_color_option), "\u{e0a0}"),
+            colored(color(options.branch_color_option), "%b")
+        );
+        self.vcs_info = Some(VcsInfo {
+            formats: format!("{}%c%u", branch),
+            actionformats: format!(
+                "{} {}%c%u",
+                branch,
+                colored(color(options.conflict_color_option), "%a")
+            ),
+            stagedstr: colored(color(options.staged_color_option), " +"),
+            unstagedstr: colored(color(options.unstaged_color_option), " !"),
+        });
+    }
+
+    /// 1 行の片側を `left` / `right` に入れるコード。
+    /// 空になり得るセグメントがあれば、表示される組み合わせごとに描画しておく
+    fn side(&mut self, i: usize, contents: &PromptContents, parts: &[Part], right: bool) -> String {
+        let var = if right { "right" } else { "left" };
+        let mut optional: Vec<&str> = parts.iter().filter_map(|p| p.optional.as_deref()).collect();
+        if optional.len() > MAX_OPTIONAL_SEGMENTS {
+            self.stubbed.push(format!(
+                "hiding empty segments on the {} of line {} (more than {} segments can be empty)",
+                var,
+                i + 1,
+                MAX_OPTIONAL_SEGMENTS
+            ));
+            optional.clear();
+        }
+        // 虹の動的なずれは書き出す時点の値で固定する
+        let hues = DynamicHues::now();
+        let render = |mask: usize| {
+            let mut bit = 0;
+            let segments = parts
+                .iter()
+                .filter(|part| match &part.optional {
+                    Some(var) if optional.contains(&var.as_str()) => {
+                        bit += 1;
+                        mask >> (bit - 1) & 1 == 1
+                    }
+                    _ => true,
+                })
+                .map(|part| part.seqs.clone())
+                .collect();
+            let mut prompt = Prompt::new(hues);
+            let builder = if right {
+                prompt.extend_right(segments);
+                prompt.render_right(contents)
+            } else {
+                prompt.extend_left(segments);
+                prompt.render_left(contents)
+            };
+            template(&builder.build())
+        };
+
+        if optional.is_empty() {
+            return format!("    {}={}\n", var, render(0));
+        }
+        let key: String = optional
+            .iter()
+            .map(|var| format!("${{${{{}:+1}}:-0}}", var))
+            .collect();
+        let mut code = format!("    case {} in\n", key);
+        for mask in 0..1usize << optional.len() {
+            let pattern: String = (0..optional.len())
+                .map(|bit| if mask >> bit & 1 == 1 { '1' } else { '0' })
+                .collect();
+            let _ = writeln!(code, "        ({}) {}={} ;;", pattern, var, render(mask));
+        }
+        code.push_str("    esac\n");
+        code
+    }
+}
+
+/// テーマを読み込んでスクリプトにし、`out` か標準出力に書き出す
+pub fn run(path: Option<&Path>, out: Option<&Path>) -> io::Result<Option<PathBuf>> {
+    let theme = manager::load_theme_from(path)?;
+    let compiled = compile(&theme);
+    if !compiled.stubbed.is_empty() {
+        eprintln!("Not available in the compiled prompt:");
+        for item in &compiled.stubbed {
+            eprintln!("  - {}", item);
+        }
+    }
+    match out {
+        Some(out) => {
+            fs::write(out, &compiled.script)?;
+            Ok(Some(out.to_path_buf()))
+        }
+        None => {
+            print!("{}", compiled.script);
+            Ok(None)
+        }
+    }
+}
@@ -1,19 +1,29 @@
 use dialoguer::theme::ColorfulTheme;
-use dialoguer::{Input, Select};
+use dialoguer::{Confirm, Input, Select};
 use std::fmt;
 use zsh_seq::NamedColor;
 
-use super::gradient::create_default_rainbow_gradient;
-use super::named_color_serde;
-use super::prompt_theme::{PromptContent, PromptContents, PromptSegmentSeparators}; // PromptSegmentSeparatorsとPromptContentをインポート
+use super::color_parse;
+use super::contrast::CONTRAST_RANGE;
+use super::gradient::{
+    Easing, Gradient, GradientStop, Interpolation, create_default_rainbow_gradient,
+    stop_color_string,
+};
+use super::per_segment::PerSegment;
+use super::preview;
+use super::prompt_theme::{AccentScope, PromptContent, PromptContents, PromptSegmentSeparators}; // PromptSegmentSeparatorsとPromptContentをインポート
+use super::rainbow::{DynamicOffset, HueDirection, HueOffset, Rainbow};
 use crate::zsh::prompt::{PromptConnection, PromptSeparation}; // crateルートからのパス
 
+/// グラデーションの編集中に表示する色の帯の幅
+const GRADIENT_BAR_WIDTH: usize = 40;
+
 // DisplayNamedColor
 struct DisplayNamedColor<'a>(Option<&'a NamedColor>); // Option<&'a NamedColor>を受け取るように変更
 impl<'a> fmt::Display for DisplayNamedColor<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Some(color) = self.0 {
-            // named_color_serde内のデシリアライザが期待する形式に合わせる
+            // color_parse::parse で読み戻せる形式にする
             let s = match color {
                 NamedColor::Code256(c) => format!("Code256({})", c),
                 NamedColor::FullColor((r, g, b)) => format!("#{:02X}{:02X}{:02X}", r, g, b),
@@ -34,44 +44,42 @@ pub fn prompt_for_named_color(
     let input = Input::with_theme(&ColorfulTheme::default())
         .with_prompt(prompt_text)
         .default(default_str.clone())
+        .validate_with(|input: &String| -> Result<(), String> {
+            if input.trim().eq_ignore_ascii_case("None") || input.trim().is_empty() {
+                Ok(())
+            } else {
+                color_parse::parse(input).map(|_| ())
+            }
+        })
         .interact_text()
         .unwrap();
 
-    if input.eq_ignore_ascii_case("None") || input.is_empty() {
+    if input.trim().eq_ignore_ascii_case("None") || input.trim().is_empty() {
         return None;
     }
 
-    if let Ok(color) = named_color_serde::deserialize_from_str(&input) {
-        Some(color)
-    } else {
-        println!("Invalid color format. Keeping default or setting to None if default was None.");
-        default_color.cloned() // 不正な入力の場合はデフォルト値を返す
+    match color_parse::parse(&input) {
+        Ok(color) => Some(color),
+        Err(e) => {
+            println!(
+                "{}. Keeping default or setting to None if default was None.",
+                e
+            );
+            default_color.cloned() // 不正な入力の場合はデフォルト値を返す
+        }
     }
 }
 
-// 新しい関数: フルカラーのRGB値をプロンプトで取得
-pub fn prompt_for_rgb_color(prompt_text: &str, default_rgb: (u8, u8, u8)) -> (u8, u8, u8) {
+// グラデーションのストップの色をプロンプトで取得。入力した書き方の色をそのまま返す
+pub fn prompt_for_stop_color(prompt_text: &str, default_color: NamedColor) -> NamedColor {
     Input::with_theme(&ColorfulTheme::default())
         .with_prompt(prompt_text)
-        .default(format!(
-            "#{:02X}{:02X}{:02X}",
-            default_rgb.0, default_rgb.1, default_rgb.2
-        ))
+        .default(stop_color_string(&default_color))
+        .validate_with(|input: &String| color_parse::parse(input).map(|_| ()))
         .interact_text()
-        .map(|s| {
-            if s.starts_with('#')
-                && s.len() == 7
-                && let (Ok(r), Ok(g), Ok(b)) = (
-                    u8::from_str_radix(&s[1..3], 16),
-                    u8::from_str_radix(&s[3..5], 16),
-                    u8::from_str_radix(&s[5..7], 16),
-                )
-            {
-                return (r, g, b);
-            }
-            default_rgb
-        })
-        .unwrap_or(default_rgb)
+        .ok()
+        .and_then(|s| color_parse::parse(&s).ok())
+        .unwrap_or(default_color)
 }
 
 pub fn configure_colors(prompt_contents: &mut PromptContents) {
@@ -89,17 +97,22 @@ pub fn configure_colors(prompt_contents: &mut PromptContents) {
     prompt_contents.color.sc =
         prompt_for_named_color("Secondary color (sc)", Some(&prompt_contents.color.sc))
             .unwrap_or(NamedColor::LightBlack);
+    prompt_contents.min_contrast = prompt_for_min_contrast(prompt_contents.min_contrast);
 
     let options = [
         "Single Color",
         "Rainbow",
         "Default Rainbow Gradient",
         "Custom Gradient",
+        "Color Cycle",
+        "Per-Separator Colors",
     ];
     let default_selection = match &prompt_contents.color.accent {
         super::color_scheme::AccentColor::Single(_) => 0,
         super::color_scheme::AccentColor::Rainbow(_) => 1,
         super::color_scheme::AccentColor::Gradient(_) => 3, // Custom Gradient に対応
+        super::color_scheme::AccentColor::Cycle(_) => 4,
+        super::color_scheme::AccentColor::PerSegment(_) => 5,
     };
 
     let selection = Select::with_theme(&ColorfulTheme::default())
@@ -115,27 +128,378 @@ pub fn configure_colors(prompt_contents: &mut PromptContents) {
                 .unwrap_or(NamedColor::LightBlack),
         ),
         1 => {
-            let color = prompt_for_named_color(
-                "Rainbow Start Color (Hex)",
-                Some(&NamedColor::FullColor((255, 0, 0))),
-            )
-            .unwrap_or(NamedColor::FullColor((255, 0, 0)));
-            super::color_scheme::AccentColor::Rainbow(color)
+            // 今の虹があればその設定から始め、編集した項目だけを変える
+            let mut rainbow = match &prompt_contents.color.accent {
+                super::color_scheme::AccentColor::Rainbow(rainbow) => rainbow.clone(),
+                _ => Rainbow::new(NamedColor::FullColor((255, 0, 0))),
+            };
+            if let Some(color) =
+                prompt_for_named_color("Rainbow Start Color (Hex)", Some(&rainbow.color))
+            {
+                rainbow.color = color;
+            }
+            super::color_scheme::AccentColor::Rainbow(configure_rainbow(rainbow))
         }
         2 => {
             // Default Rainbow Gradient
             super::color_scheme::AccentColor::Gradient(create_default_rainbow_gradient())
         }
         3 => {
-            // Custom Gradient (Existing 2-point gradient)
-            let c1_rgb = prompt_for_rgb_color("Gradient Start Color (Hex)", (0, 255, 255)); // Cyan
-            let c2_rgb = prompt_for_rgb_color("Gradient End Color (Hex)", (0, 0, 255)); // Blue
-            super::color_scheme::AccentColor::Gradient(vec![(c1_rgb, 0.0), (c2_rgb, 1.0)])
+            // Custom Gradient (今のグラデーションか、シアンから青への 2 色から編集を始める)
+            let gradient = match &prompt_contents.color.accent {
+                // `accent: []` のような空のグラデーションはストップを選べないので既定の 2 色にする
+                super::color_scheme::AccentColor::Gradient(gradient)
+                    if !gradient.stops.is_empty() =>
+                {
+                    gradient.clone()
+                }
+                _ => Gradient::new(vec![
+                    GradientStop::new(NamedColor::FullColor((0, 255, 255)), 0.0), // Cyan
+                    GradientStop::new(NamedColor::FullColor((0, 0, 255)), 1.0),   // Blue
+                ]),
+            };
+            super::color_scheme::AccentColor::Gradient(edit_gradient(gradient))
+        }
+        4 => {
+            let current = match &prompt_contents.color.accent {
+                super::color_scheme::AccentColor::Cycle(colors) => colors.clone(),
+                _ => Vec::new(),
+            };
+            super::color_scheme::AccentColor::Cycle(prompt_for_color_cycle(&current))
+        }
+        5 => {
+            let current = match &prompt_contents.color.accent {
+                super::color_scheme::AccentColor::PerSegment(per_segment) => per_segment.clone(),
+                _ => PerSegment::default(),
+            };
+            super::color_scheme::AccentColor::PerSegment(prompt_for_per_segment(current))
         }
         _ => unreachable!(),
     };
 }
 
+/// 区切りごとに順に使う色を 1 つずつ聞く。`None` で終わる
+fn prompt_for_color_cycle(current: &[NamedColor]) -> Vec<NamedColor> {
+    let mut colors = Vec::new();
+    loop {
+        let prompt = format!("Cycle color {} (None to finish)", colors.len() + 1);
+        match prompt_for_named_color(&prompt, current.get(colors.len())) {
+            Some(color) => colors.push(color),
+            None => break,
+        }
+    }
+    if colors.is_empty() {
+        colors.push(NamedColor::LightBlack);
+    }
+    colors
+}
+
+/// 区切りの番号と色を組にして聞く。番号を空にすると終わり、色を `None` にするとその番号を消す
+fn prompt_for_per_segment(mut per_segment: PerSegment) -> PerSegment {
+    loop {
+        let current = per_segment
+            .colors
+            .iter()
+            .map(|(index, color)| format!("{}: {}", index, DisplayNamedColor(Some(color))))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("Separator colors: {{{}}}", current);
+        let index: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("Separator number to set (0 = left edge, empty to finish)")
+            .allow_empty(true)
+            .validate_with(|input: &String| -> Result<(), String> {
+                if input.trim().is_empty() || input.trim().parse::<usize>().is_ok() {
+                    Ok(())
+                } else {
+                    Err("Enter a separator number such as 0, 1 or 2".to_string())
+                }
+            })
+            .interact_text()
+            .unwrap();
+        let Ok(index) = index.trim().parse::<usize>() else {
+            break;
+        };
+        let prompt = format!("Color for separator {} (None to remove)", index);
+        match prompt_for_named_color(&prompt, per_segment.colors.get(&index)) {
+            Some(color) => per_segment.colors.insert(index, color),
+            None => per_segment.colors.remove(&index),
+        };
+    }
+    if per_segment.colors.is_empty() {
+        per_segment.colors.insert(0, NamedColor::LightBlack);
+    }
+    per_segment
+}
+
+/// アクセントを行ごとに進めるか、プロンプト全体で進めるかを選ぶ
+pub fn select_accent_scope(current: AccentScope) -> AccentScope {
+    let options = [
+        (AccentScope::Line, "Line (each line restarts the accent)"),
+        (
+            AccentScope::Prompt,
+            "Prompt (one accent across all lines, using line 0's accent)",
+        ),
+        (
+            AccentScope::Snake,
+            "Snake (like Prompt, every other line runs right to left)",
+        ),
+    ];
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Accent scope")
+        .items(options.iter().map(|(_, name)| *name).collect::<Vec<_>>())
+        .default(
+            options
+                .iter()
+                .position(|(scope, _)| *scope == current)
+                .unwrap_or(0),
+        )
+        .interact()
+        .unwrap();
+    options[selection].0
+}
+
+/// 虹の色相の幅、向き、ずれを設定する
+fn configure_rainbow(mut rainbow: Rainbow) -> Rainbow {
+    rainbow.span = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Hue span in degrees (360 = full rainbow, 60 = subtle shift)")
+        .default(rainbow.span)
+        .interact_text()
+        .unwrap();
+
+    let directions = [HueDirection::Clockwise, HueDirection::Counterclockwise];
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Direction")
+        .items(
+            directions
+                .iter()
+                .map(|o| format!("{:?}", o))
+                .collect::<Vec<_>>(),
+        )
+        .default(
+            directions
+                .iter()
+                .position(|&d| d == rainbow.direction)
+                .unwrap_or(0),
+        )
+        .interact()
+        .unwrap();
+    rainbow.direction = directions[selection];
+
+    let mut offsets = vec![
+        ("None".to_string(), HueOffset::default()),
+        (
+            "Time of day (one turn per day)".to_string(),
+            HueOffset::Dynamic(DynamicOffset::Time),
+        ),
+        (
+            "Current directory".to_string(),
+            HueOffset::Dynamic(DynamicOffset::Directory),
+        ),
+        (
+            "Host name".to_string(),
+            HueOffset::Dynamic(DynamicOffset::Host),
+        ),
+    ];
+    // ファイルで書いた度数のずれも選べるようにして、そのまま残せるようにする
+    if let HueOffset::Degrees(degrees) = rainbow.offset
+        && !rainbow.offset.is_zero()
+    {
+        offsets.insert(1, (format!("{} degrees", degrees), rainbow.offset));
+    }
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Hue offset")
+        .items(
+            offsets
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .default(
+            offsets
+                .iter()
+                .position(|(_, o)| *o == rainbow.offset)
+                .unwrap_or(0),
+        )
+        .interact()
+        .unwrap();
+    rainbow.offset = offsets[selection].1;
+    rainbow
+}
+
+/// グラデーションの色の混ぜ方を選ぶ
+fn select_interpolation(current: Interpolation) -> Interpolation {
+    let options = Interpolation::ALL;
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Interpolation (oklch keeps long gradients vivid)")
+        .items(
+            options
+                .iter()
+                .map(|o| format!("{:?}", o))
+                .collect::<Vec<_>>(),
+        )
+        .default(options.iter().position(|&o| o == current).unwrap_or(0))
+        .interact()
+        .unwrap();
+    options[selection]
+}
+
+/// グラデーションのストップを追加、削除、移動しながら編集する。変更のたびに色の帯を表示する
+fn edit_gradient(mut gradient: Gradient) -> Gradient {
+    loop {
+        gradient.stops.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        println!("\n{}", preview::gradient_bar(&gradient, GRADIENT_BAR_WIDTH));
+        let stops = gradient
+            .stops
+            .iter()
+            .enumerate()
+            .map(|(i, stop)| format!("{}. {}", i + 1, stop))
+            .collect::<Vec<_>>();
+        println!("{}", stops.join("\n"));
+
+        let options = [
+            "Add Stop",
+            "Remove Stop",
+            "Move Stop",
+            "Change Stop Color",
+            "Change Stop Easing",
+            "Distribute Evenly",
+            "Interpolation",
+            "Done",
+        ];
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Edit gradient")
+            .items(options)
+            .default(0)
+            .interact()
+            .unwrap();
+        match selection {
+            0 => {
+                let pos =
+                    prompt_for_stop_position("Position of the new stop", widest_gap(&gradient));
+                let color = prompt_for_stop_color("Color of the new stop", gradient.get(pos));
+                gradient.stops.push(GradientStop::new(color, pos));
+            }
+            1 => {
+                if gradient.stops.len() <= 2 {
+                    println!("A gradient needs at least two stops.");
+                    continue;
+                }
+                let i = select_stop(&gradient, "Stop to remove");
+                gradient.stops.remove(i);
+            }
+            2 => {
+                let i = select_stop(&gradient, "Stop to move");
+                gradient.stops[i].pos =
+                    prompt_for_stop_position("New position", gradient.stops[i].pos);
+            }
+            3 => {
+                let i = select_stop(&gradient, "Stop to recolor");
+                gradient.stops[i].color =
+                    prompt_for_stop_color("New color", gradient.stops[i].color);
+            }
+            4 => {
+                let i = select_stop(&gradient, "Stop whose easing to change");
+                let easings = Easing::ALL;
+                let current = gradient.stops[i].easing;
+                let selection = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Easing up to the next stop")
+                    .items(easings.iter().map(|e| e.as_str()).collect::<Vec<_>>())
+                    .default(easings.iter().position(|&e| e == current).unwrap_or(0))
+                    .interact()
+                    .unwrap();
+                gradient.stops[i].easing = easings[selection];
+            }
+            5 => {
+                let last = gradient.stops.len().saturating_sub(1).max(1) as f32;
+                for (i, stop) in gradient.stops.iter_mut().enumerate() {
+                    stop.pos = i as f32 / last;
+                }
+            }
+            6 => gradient.interpolation = select_interpolation(gradient.interpolation),
+            _ => return gradient,
+        }
+    }
+}
+
+/// ストップを 1 つ選ぶ。選択肢には番号と色、位置を表示する
+fn select_stop(gradient: &Gradient, prompt: &str) -> usize {
+    let items = gradient
+        .stops
+        .iter()
+        .enumerate()
+        .map(|(i, stop)| format!("{}. {}", i + 1, stop))
+        .collect::<Vec<_>>();
+    Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(&items)
+        .default(0)
+        .interact()
+        .unwrap()
+}
+
+/// ストップの位置 (0.0〜1.0) を聞く
+fn prompt_for_stop_position(prompt: &str, default: f32) -> f32 {
+    Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("{} (0.0-1.0)", prompt))
+        .default((default * 100.0).round() / 100.0)
+        .validate_with(|pos: &f32| -> Result<(), String> {
+            if (0.0..=1.0).contains(pos) {
+                Ok(())
+            } else {
+                Err("expected a position between 0.0 and 1.0".to_string())
+            }
+        })
+        .interact_text()
+        .unwrap()
+}
+
+/// 隣り合うストップの間が最も広い所の中央。新しいストップの位置の既定値に使う
+fn widest_gap(gradient: &Gradient) -> f32 {
+    let mut positions = vec![0.0];
+    positions.extend(gradient.stops.iter().map(|stop| stop.pos.clamp(0.0, 1.0)));
+    positions.push(1.0);
+    positions.sort_by(f32::total_cmp);
+    positions
+        .windows(2)
+        .max_by(|a, b| (a[1] - a[0]).total_cmp(&(b[1] - b[0])))
+        .map_or(0.5, |pair| (pair[0] + pair[1]) / 2.0)
+}
+
+/// セグメントの文字に保つコントラスト比。変えると答えたときだけ尋ね、それ以外は今の値を残す。
+/// 空欄で無効にする
+fn prompt_for_min_contrast(current: Option<f32>) -> Option<f32> {
+    let prompt = match current {
+        Some(ratio) => format!("Change the minimum text contrast (currently {})?", ratio),
+        None => "Set a minimum text contrast?".to_string(),
+    };
+    let change = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .unwrap();
+    if !change {
+        return current;
+    }
+    let input = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Minimum text contrast (1-21, e.g. 4.5; empty to disable)")
+        .default(current.map(|ratio| ratio.to_string()).unwrap_or_default())
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), String> {
+            match input.trim() {
+                "" => Ok(()),
+                s => s
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|ratio| CONTRAST_RANGE.contains(ratio))
+                    .map(|_| ())
+                    .ok_or_else(|| "expected a number between 1 and 21".to_string()),
+            }
+        })
+        .interact_text()
+        .unwrap();
+    input.trim().parse().ok()
+}
+
 pub fn configure_prompt_content_colors(prompt_content: &mut PromptContent) {
     println!("\n--- Configure Prompt Content Colors ---");
 
@@ -158,20 +522,7 @@ pub fn configure_prompt_content_colors(prompt_content: &mut PromptContent) {
 
 pub fn configure_connection(prompt_contents: &mut PromptContents) {
     println!("\n--- Configure Connection ---");
-    let options = [
-        PromptConnection::None,
-        PromptConnection::Line,
-        PromptConnection::Double,
-        PromptConnection::Bold,
-        PromptConnection::Dashed,
-        PromptConnection::Dotted,
-        PromptConnection::Dot,
-        PromptConnection::Bullet,
-        PromptConnection::Wave,
-        PromptConnection::ZigZag,
-        PromptConnection::Bar,
-        PromptConnection::Gradient,
-    ];
+    let options = PromptConnection::ALL;
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Choose style")
         .items(
@@ -193,18 +544,7 @@ pub fn configure_connection(prompt_contents: &mut PromptContents) {
 
 // PromptSeparationの選択UIをヘルパー関数として抽出
 fn select_prompt_separation_style(current_style: &PromptSeparation) -> PromptSeparation {
-    let options = [
-        PromptSeparation::Block,
-        PromptSeparation::Sharp,
-        PromptSeparation::Slash,
-        PromptSeparation::BackSlash,
-        PromptSeparation::Round,
-        PromptSeparation::Blur,
-        PromptSeparation::Flame,
-        PromptSeparation::Pixel,
-        PromptSeparation::Wave,
-        PromptSeparation::Lego,
-    ];
+    let options = PromptSeparation::ALL;
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Choose style")
         .items(
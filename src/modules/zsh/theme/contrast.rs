@@ -0,0 +1,108 @@
+use std::ops::RangeInclusive;
+use zsh_seq::{NamedColor, ZshSequence};
+
+use super::gradient::{named_color_to_rgb, oklab_to_rgb, rgb_to_oklab, srgb_to_linear};
+
+/// WCAG のコントラスト比が取り得る範囲
+pub const CONTRAST_RANGE: RangeInclusive<f32> = 1.0..=21.0;
+
+const WHITE: (u8, u8, u8) = (255, 255, 255);
+const BLACK: (u8, u8, u8) = (0, 0, 0);
+
+/// WCAG の相対輝度
+fn luminance((r, g, b): (u8, u8, u8)) -> f32 {
+    0.2126 * srgb_to_linear(r) + 0.7152 * srgb_to_linear(g) + 0.0722 * srgb_to_linear(b)
+}
+
+/// WCAG のコントラスト比 (1〜21)
+pub fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// `bg` の上で `min` 以上のコントラスト比になる文字色。
+/// 足りなければ OKLab の明度を白か黒のよく見える方へ少しずつ動かし、元の色に最も近いものを選ぶ
+pub fn readable(fg: NamedColor, bg: NamedColor, min: f32) -> NamedColor {
+    let fg_rgb = named_color_to_rgb(&fg);
+    let bg_rgb = named_color_to_rgb(&bg);
+    if contrast_ratio(fg_rgb, bg_rgb) >= min {
+        return fg;
+    }
+    let target = if contrast_ratio(WHITE, bg_rgb) >= contrast_ratio(BLACK, bg_rgb) {
+        WHITE
+    } else {
+        BLACK
+    };
+    // t = 0 で元の色、t = 1 で白か黒。明度を寄せながら彩度も落とす
+    let (l, a, b) = rgb_to_oklab(fg_rgb);
+    let (target_l, _, _) = rgb_to_oklab(target);
+    let at = |t: f32| oklab_to_rgb((l + (target_l - l) * t, a * (1.0 - t), b * (1.0 - t)));
+    if contrast_ratio(at(1.0), bg_rgb) < min {
+        return NamedColor::FullColor(target);
+    }
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    for _ in 0..16 {
+        let mid = (lo + hi) / 2.0;
+        if contrast_ratio(at(mid), bg_rgb) >= min {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    NamedColor::FullColor(at(hi))
+}
+
+/// セグメントの中身の文字色を、背景 `bg` の上で `min` 以上のコントラスト比になるよう直す。
+/// 色の指定が無い文字は端末の既定の文字色で描かれ、その色は分からないので、
+/// セグメントの始めと `ForegroundColorEnd` の後には `default_fg` を読めるよう直した色を必ず付ける
+pub fn apply(
+    content: &[ZshSequence],
+    bg: NamedColor,
+    default_fg: NamedColor,
+    min: f32,
+) -> Vec<ZshSequence> {
+    let mut out = Vec::with_capacity(content.len() + 2);
+    let mut current_fg = None;
+    let mut current_bg = bg;
+    // 出力側で有効な文字色。同じ色を続けて書かないよう RGB で覚えておく
+    let mut emitted: Option<(u8, u8, u8)> = None;
+    let mut update = |out: &mut Vec<ZshSequence>, fg: Option<NamedColor>, bg: NamedColor| {
+        let color = readable(fg.unwrap_or(default_fg), bg, min);
+        let rgb = Some(named_color_to_rgb(&color));
+        if rgb == emitted {
+            return;
+        }
+        emitted = rgb;
+        out.push(ZshSequence::ForegroundColor(color));
+    };
+
+    update(&mut out, current_fg, current_bg);
+    for seq in content {
+        match seq {
+            ZshSequence::ForegroundColor(color) => {
+                current_fg = Some(*color);
+                update(&mut out, current_fg, current_bg);
+            }
+            ZshSequence::ForegroundColorEnd => {
+                current_fg = None;
+                update(&mut out, current_fg, current_bg);
+            }
+            ZshSequence::BackgroundColor(color) => {
+                current_bg = *color;
+                out.push(seq.clone());
+                update(&mut out, current_fg, current_bg);
+            }
+            ZshSequence::BackgroundColorEnd => {
+                current_bg = bg;
+                out.push(seq.clone());
+                update(&mut out, current_fg, current_bg);
+            }
+            seq => out.push(seq.clone()),
+        }
+    }
+    // 付け足した色は中身の後ろに残さない
+    if current_fg.is_none() {
+        out.push(ZshSequence::ForegroundColorEnd);
+    }
+    out
+}
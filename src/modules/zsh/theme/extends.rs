@@ -0,0 +1,230 @@
+use serde_yaml::{Mapping, Value, value::TaggedValue};
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{manager::ThemeFormat, migrate, palette, prompt_theme::PromptTheme};
+
+const EXTENDS_KEY: &str = "extends";
+/// 行ごとにマージするリスト。それ以外のリストは丸ごと置き換える
+const LINES_KEY: &str = "prompt_contents_list";
+
+/// `extends` があれば継承元を読み込み、その上に `value` を重ねる
+///
+/// `path` は `value` を読み込んだファイルで、相対パスの基準になる。
+pub fn resolve(value: Value, format: ThemeFormat, path: &Path) -> Result<Value, String> {
+    resolve_with(value, format, path, &mut vec![canonical(path)])
+}
+
+/// `extends` の値（プリセット名またはファイルパス）から継承元のテーマを読み込む
+pub fn load_base(extends: &str, path: &Path) -> Result<PromptTheme, String> {
+    load_base_with(extends, path, &mut vec![canonical(path)])
+}
+
+fn resolve_with(
+    value: Value,
+    format: ThemeFormat,
+    path: &Path,
+    visited: &mut Vec<PathBuf>,
+) -> Result<Value, String> {
+    let extends = match value.get(EXTENDS_KEY) {
+        None => return Ok(value),
+        Some(Value::String(extends)) => extends.clone(),
+        Some(other) => return Err(format!("`extends` must be a string, found {:?}", other)),
+    };
+    let base = load_base_with(&extends, path, visited)?;
+    let mut base_value = format.to_value(&base)?;
+    if let Value::Mapping(map) = &mut base_value {
+        map.remove(EXTENDS_KEY);
+    }
+    // 継承元のパレットの色を参照に戻し、上書きしたパレットで解決し直せるようにする
+    palette::unresolve(&mut base_value, &base.palette_references);
+    Ok(merge(base_value, value, false))
+}
+
+fn load_base_with(
+    extends: &str,
+    path: &Path,
+    visited: &mut Vec<PathBuf>,
+) -> Result<PromptTheme, String> {
+    if let Some(theme) = PromptTheme::preset(extends) {
+        return Ok(theme);
+    }
+
+    let base_path = base_path(extends, path);
+    let key = canonical(&base_path);
+    if visited.contains(&key) {
+        return Err(format!("circular `extends` through {:?}", base_path));
+    }
+    visited.push(key);
+
+    let content = fs::read_to_string(&base_path)
+        .map_err(|e| format!("Failed to read base theme {:?}: {}", base_path, e))?;
+    let format = ThemeFormat::from_path(&base_path);
+    let value = format
+        .parse(&content)
+        .map_err(|e| format!("Failed to parse base theme {:?}: {}", base_path, e))?;
+    let value = migrate::migrate(value, format)
+        .map_err(|e| format!("Failed to migrate base theme {:?}: {}", base_path, e))?
+        .value;
+    let mut value = resolve_with(value, format, &base_path, visited)?;
+    let references = palette::resolve(&mut value, format).map_err(|e| {
+        format!(
+            "Failed to resolve palette of base theme {:?}: {}",
+            base_path, e
+        )
+    })?;
+    let mut theme: PromptTheme = format
+        .from_value(value)
+        .map_err(|e| format!("Failed to deserialize base theme {:?}: {}", base_path, e))?;
+    theme.palette_references = references;
+    Ok(theme)
+}
+
+/// プリセットでない `extends` の値が指すファイル。`~` や環境変数を展開し、`path` のディレクトリを基準にする
+pub fn base_path(extends: &str, path: &Path) -> PathBuf {
+    let expanded = shellexpand::full(extends).unwrap_or(Cow::Borrowed(extends));
+    match path.parent() {
+        Some(dir) => dir.join(expanded.as_ref()),
+        None => PathBuf::from(expanded.as_ref()),
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// マージする値がテーマのどこにあるか
+#[derive(Clone, Copy, PartialEq)]
+enum Position {
+    /// `prompt_contents_list`
+    Lines,
+    /// TOML/JSON で `{tag: 中身}` と書かれる enum の値
+    Variant,
+    Other,
+}
+
+fn position_of(key: &Value) -> Position {
+    match key.as_str() {
+        Some(LINES_KEY) => Position::Lines,
+        // `accent`、組み込みコマンドの `command`、Git の色
+        Some("accent" | "command") => Position::Variant,
+        Some(key) if key.ends_with("_color_option") => Position::Variant,
+        _ => Position::Other,
+    }
+}
+
+/// `overrides` を `base` に深くマージする
+///
+/// マッピングはキーごと、`prompt_contents_list` は行番号ごとにマージする。
+/// 行を `null` にするとその行を取り除く。その他の値は上書きする。
+pub fn merge(base: Value, overrides: Value, lines: bool) -> Value {
+    let position = if lines {
+        Position::Lines
+    } else {
+        Position::Other
+    };
+    merge_at(base, overrides, position)
+}
+
+fn merge_at(base: Value, overrides: Value, position: Position) -> Value {
+    match (base, overrides) {
+        (Value::Mapping(base), Value::Mapping(overrides))
+            if !is_other_variant(&base, &overrides, position) =>
+        {
+            let mut merged = base;
+            for (key, value) in overrides {
+                let position = position_of(&key);
+                let value = match merged.remove(&key) {
+                    Some(base_value) => merge_at(base_value, value, position),
+                    None => value,
+                };
+                merged.insert(key, value);
+            }
+            Value::Mapping(merged)
+        }
+        (Value::Sequence(base), Value::Sequence(overrides)) if position == Position::Lines => {
+            let len = base.len().max(overrides.len());
+            let mut base = base.into_iter();
+            let mut overrides = overrides.into_iter();
+            let merged = (0..len)
+                .map(|_| match (base.next(), overrides.next()) {
+                    (Some(base), Some(line)) if !line.is_null() => {
+                        merge_at(base, line, Position::Other)
+                    }
+                    (None, Some(line)) => line,
+                    (Some(base), None) => base,
+                    _ => Value::Null,
+                })
+                .filter(|line| !line.is_null())
+                .collect();
+            Value::Sequence(merged)
+        }
+        (Value::Tagged(base), Value::Tagged(overrides)) if base.tag == overrides.tag => {
+            Value::Tagged(Box::new(TaggedValue {
+                tag: overrides.tag,
+                value: merge_at(base.value, overrides.value, Position::Other),
+            }))
+        }
+        (_, overrides) => overrides,
+    }
+}
+
+/// `full` を `base` にマージすると `full` に戻るような最小の差分を返す
+pub fn diff(base: &Value, full: &Value) -> Value {
+    diff_value(base, full, Position::Other).unwrap_or_else(|| Value::Mapping(Mapping::new()))
+}
+
+fn diff_value(base: &Value, full: &Value, position: Position) -> Option<Value> {
+    if base == full {
+        return None;
+    }
+    match (base, full) {
+        (Value::Mapping(base), Value::Mapping(full)) if !is_other_variant(base, full, position) => {
+            let mut out = Mapping::new();
+            for (key, value) in full {
+                match base.get(key) {
+                    Some(base_value) => {
+                        if let Some(d) = diff_value(base_value, value, position_of(key)) {
+                            out.insert(key.clone(), d);
+                        }
+                    }
+                    None => {
+                        out.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            // 空のリストはシリアライズ時に省略されるため、明示的に空にする
+            for (key, value) in base {
+                if !full.contains_key(key) && value.is_sequence() {
+                    out.insert(key.clone(), Value::Sequence(Vec::new()));
+                }
+            }
+            Some(Value::Mapping(out))
+        }
+        (Value::Sequence(base), Value::Sequence(full)) if position == Position::Lines => {
+            let len = base.len().max(full.len());
+            let out = (0..len)
+                .map(|i| match (base.get(i), full.get(i)) {
+                    (Some(base), Some(line)) => diff_value(base, line, Position::Other)
+                        .unwrap_or(Value::Mapping(Mapping::new())),
+                    (None, Some(line)) => line.clone(),
+                    _ => Value::Null,
+                })
+                .collect();
+            Some(Value::Sequence(out))
+        }
+        _ => Some(full.clone()),
+    }
+}
+
+/// TOML/JSON の `{tag: 中身}` 形式の enum で、異なるバリアント同士かどうか。
+/// その場合はマージせずに置き換える。`palette` や `envs` のような普通のマッピングは常にマージする
+fn is_other_variant(base: &Mapping, overrides: &Mapping, position: Position) -> bool {
+    position == Position::Variant
+        && base.len() == 1
+        && overrides.len() == 1
+        && base.keys().next() != overrides.keys().next()
+}
@@ -0,0 +1,143 @@
+use clap::ValueEnum;
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io,
+};
+use zsh_seq::NamedColor;
+
+use super::{
+    color_parse,
+    color_scheme::{AccentColor, PromptColorScheme},
+    gradient::{Gradient, GradientStop, hsl_to_rgb, named_color_to_rgb, rgb_to_hsl},
+    manager, preview,
+};
+
+/// 種の色から色相をどう広げるか
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Scheme {
+    /// 隣り合う色相 (±30°)
+    #[default]
+    Analogous,
+    /// 120° ずつ離れた 3 色
+    Triadic,
+    /// 反対側の色相 (180°)
+    Complementary,
+}
+
+impl Scheme {
+    /// アクセントのグラデーションに使う、種の色相からのずれ
+    fn hue_offsets(self) -> &'static [f32] {
+        match self {
+            Self::Analogous => &[-30.0, 0.0, 30.0],
+            Self::Triadic => &[0.0, 120.0, 240.0],
+            Self::Complementary => &[0.0, 90.0, 180.0],
+        }
+    }
+}
+
+fn hsl_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
+    hsl_to_rgb(h.rem_euclid(360.0), s.clamp(0.0, 1.0), l.clamp(0.0, 1.0))
+}
+
+fn hsl(h: f32, s: f32, l: f32) -> NamedColor {
+    NamedColor::FullColor(hsl_rgb(h, s, l))
+}
+
+/// 種の色から配色とアクセントのグラデーションを作る。
+/// `dark` なら暗い背景向け、そうでなければ明るい背景向けにする
+pub fn generate(seed: (u8, u8, u8), scheme: Scheme, dark: bool) -> PromptColorScheme {
+    let (h, s, _) = rgb_to_hsl(seed.0, seed.1, seed.2);
+    // 白は彩度が求まらないので無彩色として扱う
+    let s = if s.is_nan() { 0.0 } else { s };
+    // 無彩色の種はアクセントも無彩色のままにする
+    let vivid = if s < 0.05 { s } else { s.max(0.6) };
+    let (bg, fg, accent, sc) = if dark {
+        (0.12, 0.90, 0.60, 0.45)
+    } else {
+        (0.92, 0.15, 0.40, 0.60)
+    };
+
+    let offsets = scheme.hue_offsets();
+    let stops = offsets
+        .iter()
+        .enumerate()
+        .map(|(i, offset)| {
+            GradientStop::new(
+                NamedColor::FullColor(hsl_rgb(h + offset, vivid, accent)),
+                i as f32 / (offsets.len() - 1) as f32,
+            )
+        })
+        .collect();
+
+    PromptColorScheme {
+        bg: hsl(h, s * 0.25, bg),
+        fg: hsl(h, s.min(0.1), fg),
+        pc: hsl(h, vivid, accent),
+        sc: hsl(h, s * 0.2, sc),
+        accent: AccentColor::Gradient(Gradient::new(stops)),
+        accent_which: Default::default(),
+    }
+}
+
+/// 乱数の種の色。彩度と明度は見やすい範囲にする
+fn random_seed() -> (u8, u8, u8) {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default(),
+    );
+    let n = hasher.finish();
+    let hue = (n % 360) as f32;
+    let saturation = 0.6 + ((n >> 16) % 30) as f32 / 100.0;
+    hsl_to_rgb(hue, saturation, 0.5)
+}
+
+/// 配色を作って見本を表示し、確認してから使用中のテーマの全ての行に適用する。
+/// `yes` なら確認せずに適用する
+pub fn run(seed: Option<&str>, scheme: Scheme, dark: bool, yes: bool) -> io::Result<()> {
+    let seed = match seed {
+        Some(seed) => named_color_to_rgb(&color_parse::parse(seed).map_err(io::Error::other)?),
+        None => random_seed(),
+    };
+    let colors = generate(seed, scheme, dark);
+
+    let mut theme = manager::load_theme_from(None)?;
+    for contents in &mut theme.prompt_contents_list {
+        contents.color = PromptColorScheme {
+            accent_which: contents.color.accent_which,
+            ..colors.clone()
+        };
+        // 明暗ごとの配色が残っていると新しい配色より優先されてしまう
+        contents.color_light = None;
+        contents.color_dark = None;
+    }
+    theme.transient_color.pc = colors.pc;
+    theme.transient_color_light = None;
+    theme.transient_color_dark = None;
+
+    println!(
+        "Seed: #{:02X}{:02X}{:02X} ({:?}, {})",
+        seed.0,
+        seed.1,
+        seed.2,
+        scheme,
+        if dark { "dark" } else { "light" }
+    );
+    println!("{}", preview::render(&theme));
+    let apply = yes
+        || Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Apply these colours?")
+            .default(true)
+            .interact()
+            .unwrap_or(false);
+    if apply {
+        manager::save_theme(&theme)?;
+    } else {
+        println!("The colours were not applied.");
+    }
+    Ok(())
+}
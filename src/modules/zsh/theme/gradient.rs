@@ -1,34 +1,256 @@
 use serde::de::{self, Visitor};
-use serde::{Deserializer, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use zsh_seq::NamedColor;
 
-pub type GradientPart = Vec<((u8, u8, u8), f32)>;
+use super::{color_parse, named_color_serde};
+
+/// グラデーションの色の混ぜ方
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Interpolation {
+    /// sRGB の値をそのまま混ぜる (従来の動作)
+    #[default]
+    Srgb,
+    /// 線形 RGB で混ぜる。明るさの変化が自然になる
+    LinearRgb,
+    /// HSL で色相の近い方を回る
+    Hsl,
+    /// OKLab で混ぜる。中間の色がくすみにくい
+    Oklab,
+    /// OKLCH で色相の近い方を回る。鮮やかさを保つ
+    Oklch,
+}
+
+impl Interpolation {
+    pub const ALL: [Self; 5] = [
+        Self::Srgb,
+        Self::LinearRgb,
+        Self::Hsl,
+        Self::Oklab,
+        Self::Oklch,
+    ];
+
+    /// `a` から `b` へ `t` (0.0〜1.0) だけ進んだ色
+    pub fn mix(self, a: (u8, u8, u8), b: (u8, u8, u8), t: f32) -> (u8, u8, u8) {
+        let lerp = |x: f32, y: f32| x + (y - x) * t;
+        match self {
+            Self::Srgb => lerp_rgb_color(a, b, t),
+            Self::LinearRgb => {
+                let channel =
+                    |x: u8, y: u8| linear_to_srgb(lerp(srgb_to_linear(x), srgb_to_linear(y)));
+                (channel(a.0, b.0), channel(a.1, b.1), channel(a.2, b.2))
+            }
+            Self::Hsl => {
+                let (h1, s1, l1) = rgb_to_hsl(a.0, a.1, a.2);
+                let (h2, s2, l2) = rgb_to_hsl(b.0, b.1, b.2);
+                // 灰色には色相が無いので、もう一方の色相を使う
+                let (h1, h2) = match (s1 == 0.0, s2 == 0.0) {
+                    (true, false) => (h2, h2),
+                    (false, true) => (h1, h1),
+                    _ => (h1, h2),
+                };
+                hsl_to_rgb(lerp_hue(h1, h2, t), lerp(s1, s2), lerp(l1, l2))
+            }
+            Self::Oklab => {
+                let (l1, a1, b1) = rgb_to_oklab(a);
+                let (l2, a2, b2) = rgb_to_oklab(b);
+                oklab_to_rgb((lerp(l1, l2), lerp(a1, a2), lerp(b1, b2)))
+            }
+            Self::Oklch => {
+                let (l1, c1, h1) = oklab_to_oklch(rgb_to_oklab(a));
+                let (l2, c2, h2) = oklab_to_oklch(rgb_to_oklab(b));
+                let (h1, h2) = match (c1 < ACHROMATIC, c2 < ACHROMATIC) {
+                    (true, false) => (h2, h2),
+                    (false, true) => (h1, h1),
+                    _ => (h1, h2),
+                };
+                oklab_to_rgb(oklch_to_oklab((
+                    lerp(l1, l2),
+                    lerp(c1, c2),
+                    lerp_hue(h1, h2, t),
+                )))
+            }
+        }
+    }
+}
+
+/// OKLCH でこれより彩度が低い色は灰色とみなす
+const ACHROMATIC: f32 = 0.02;
+
+/// 色相 (度) を近い方の向きに補間する
+fn lerp_hue(h1: f32, h2: f32, t: f32) -> f32 {
+    let diff = (h2 - h1 + 540.0).rem_euclid(360.0) - 180.0;
+    (h1 + diff * t).rem_euclid(360.0)
+}
+
+fn oklab_to_oklch((l, a, b): (f32, f32, f32)) -> (f32, f32, f32) {
+    (l, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360.0))
+}
+
+fn oklch_to_oklab((l, c, h): (f32, f32, f32)) -> (f32, f32, f32) {
+    let h = h.to_radians();
+    (l, c * h.cos(), c * h.sin())
+}
+
+/// グラデーションのストップから次のストップまでの進み方
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// ゆっくり始まる
+    EaseIn,
+    /// ゆっくり終わる
+    EaseOut,
+    /// ゆっくり始まりゆっくり終わる
+    EaseInOut,
+}
+
+impl Easing {
+    pub const ALL: [Self; 4] = [Self::Linear, Self::EaseIn, Self::EaseOut, Self::EaseInOut];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::EaseIn => "ease-in",
+            Self::EaseOut => "ease-out",
+            Self::EaseInOut => "ease-in-out",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|easing| easing.as_str().eq_ignore_ascii_case(s.trim()))
+    }
+
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// グラデーションの 1 つの色と位置。`easing` はこのストップから次のストップまでに使う。
+/// 色は書かれたとおりの `NamedColor` で持ち、RGB には色を計算するときに直す
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    pub color: NamedColor,
+    pub pos: f32,
+    pub easing: Easing,
+}
+
+impl GradientStop {
+    pub fn new(color: NamedColor, pos: f32) -> Self {
+        Self {
+            color,
+            pos,
+            easing: Easing::default(),
+        }
+    }
+
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        named_color_to_rgb(&self.color)
+    }
+}
+
+impl fmt::Display for GradientStop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", stop_color_string(&self.color), self.pos)?;
+        if self.easing != Easing::Linear {
+            write!(f, ":{}", self.easing.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+/// ストップの色の書き方。フルカラーは従来どおり `#RRGGBB`、それ以外は色の名前や `Code256(n)` にする
+pub fn stop_color_string(color: &NamedColor) -> String {
+    match color {
+        NamedColor::FullColor((r, g, b)) => format!("#{:02X}{:02X}{:02X}", r, g, b),
+        color => named_color_serde::to_string(color),
+    }
+}
+
+/// 複数の色を位置で繋いだグラデーション
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Gradient {
+    pub stops: Vec<GradientStop>,
+    pub interpolation: Interpolation,
+}
+
+impl Gradient {
+    pub fn new(stops: Vec<GradientStop>) -> Self {
+        Self {
+            stops,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    /// `progress` (0.0〜1.0) の位置の色。ストップが無ければ White
+    pub fn get(&self, progress: f32) -> NamedColor {
+        let mut stops = self.stops.clone();
+        stops.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        let (Some(first), Some(last)) = (stops.first(), stops.last()) else {
+            return NamedColor::White;
+        };
+        if progress <= first.pos {
+            return first.color;
+        }
+        if progress >= last.pos {
+            return last.color;
+        }
+        for pair in stops.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            if progress >= from.pos && progress <= to.pos {
+                let t = (progress - from.pos) / (to.pos - from.pos);
+                let t = from.easing.apply(t);
+                return NamedColor::FullColor(self.interpolation.mix(from.rgb(), to.rgb(), t));
+            }
+        }
+        first.color
+    }
+}
 
 // --- Gradient用のカスタムシリアライズ/デシリアライズ ---
 
-pub fn serialize_gradient<S>(stops: &GradientPart, serializer: S) -> Result<S::Ok, S::Error>
+/// 既定の混ぜ方なら従来どおりストップの列、それ以外は `{interpolation, stops}` として書く
+pub fn serialize_gradient<S>(gradient: &Gradient, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    use serde::ser::SerializeSeq;
-    let mut seq = serializer.serialize_seq(Some(stops.len()))?;
-    for (rgb, pos) in stops {
-        // フルカラー形式をHex文字列に変換して保存
-        seq.serialize_element(&format!("#{:02X}{:02X}{:02X}:{}", rgb.0, rgb.1, rgb.2, pos))?;
+    use serde::ser::{SerializeMap, SerializeSeq};
+    // フルカラー形式をHex文字列に変換して保存
+    let stops: Vec<String> = gradient.stops.iter().map(ToString::to_string).collect();
+    if gradient.interpolation == Interpolation::default() {
+        let mut seq = serializer.serialize_seq(Some(stops.len()))?;
+        for stop in &stops {
+            seq.serialize_element(stop)?;
+        }
+        seq.end()
+    } else {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("interpolation", &gradient.interpolation)?;
+        map.serialize_entry("stops", &stops)?;
+        map.end()
     }
-    seq.end()
 }
 
-pub fn deserialize_gradient<'de, D>(deserializer: D) -> Result<GradientPart, D::Error>
+pub fn deserialize_gradient<'de, D>(deserializer: D) -> Result<Gradient, D::Error>
 where
     D: Deserializer<'de>,
 {
     struct GradientVisitor;
     impl<'de> Visitor<'de> for GradientVisitor {
-        type Value = GradientPart;
+        type Value = Gradient;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("a sequence of '#RRGGBB:stop' strings")
+            formatter.write_str(
+                "a sequence of 'COLOR:stop' strings, or a map with `interpolation` and `stops`",
+            )
         }
 
         fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -37,24 +259,68 @@ where
         {
             let mut stops = Vec::new();
             while let Some(s) = seq.next_element::<String>()? {
-                let parts: Vec<&str> = s.split(':').collect();
-                if parts.len() == 2 && parts[0].starts_with('#') && parts[0].len() == 7 {
-                    let r = u8::from_str_radix(&parts[0][1..3], 16).map_err(de::Error::custom)?;
-                    let g = u8::from_str_radix(&parts[0][3..5], 16).map_err(de::Error::custom)?;
-                    let b = u8::from_str_radix(&parts[0][5..7], 16).map_err(de::Error::custom)?;
-                    let pos = parts[1].parse::<f32>().map_err(de::Error::custom)?;
-                    stops.push(((r, g, b), pos));
-                } else {
-                    return Err(de::Error::custom(format!(
-                        "Invalid gradient stop format: {}",
-                        s
-                    )));
+                stops.push(parse_gradient_stop(&s).map_err(de::Error::custom)?);
+            }
+            Ok(Gradient::new(stops))
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: de::MapAccess<'de>,
+        {
+            let mut gradient = Gradient::default();
+            let mut has_stops = false;
+            while let Some(key) = map.next_key::<String>()? {
+                match key.as_str() {
+                    "interpolation" => gradient.interpolation = map.next_value()?,
+                    "stops" => {
+                        gradient.stops = map
+                            .next_value::<Vec<String>>()?
+                            .iter()
+                            .map(|s| parse_gradient_stop(s))
+                            .collect::<Result<_, _>>()
+                            .map_err(de::Error::custom)?;
+                        has_stops = true;
+                    }
+                    other => {
+                        return Err(de::Error::unknown_field(other, &["interpolation", "stops"]));
+                    }
                 }
             }
-            Ok(stops)
+            if !has_stops {
+                return Err(de::Error::missing_field("stops"));
+            }
+            Ok(gradient)
         }
     }
-    deserializer.deserialize_seq(GradientVisitor)
+    deserializer.deserialize_any(GradientVisitor)
+}
+
+/// `"COLOR:pos"` 形式のグラデーションストップを1つ解析する。
+/// COLOR は `color_parse::parse` が読める書き方なら何でもよい。
+/// `"#RRGGBB:pos:ease-in"` のように次のストップまでの進み方も書ける
+pub fn parse_gradient_stop(s: &str) -> Result<GradientStop, String> {
+    let invalid = || format!("Invalid gradient stop format: {}", s);
+    // 色の書き方に `:` は含まれないので、後ろから区切る
+    let (rest, last) = s.rsplit_once(':').ok_or_else(invalid)?;
+    let (color, pos, easing) = match rest.rsplit_once(':') {
+        Some((color, pos)) if pos.trim().parse::<f32>().is_ok() => {
+            let easing = Easing::parse(last).ok_or_else(|| {
+                format!(
+                    "unknown easing `{}` (expected linear, ease-in, ease-out or ease-in-out)",
+                    last
+                )
+            })?;
+            (color, pos, easing)
+        }
+        _ => (rest, last, Easing::default()),
+    };
+    let color = color_parse::parse(color).map_err(|e| format!("{}: {}", s, e))?;
+    let pos = pos
+        .trim()
+        .parse::<f32>()
+        .map_err(|e| format!("{}: {}", s, e))?;
+    Ok(GradientStop { color, pos, easing })
 }
 
 // --- 色計算ロジック ---
@@ -123,14 +389,124 @@ pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
     )
 }
 
-pub fn create_default_rainbow_gradient() -> GradientPart {
-    vec![
-        ((255, 0, 0), 0.0),    // Red
-        ((255, 127, 0), 0.16), // Orange
-        ((255, 255, 0), 0.32), // Yellow
-        ((0, 255, 0), 0.48),   // Green
-        ((0, 0, 255), 0.64),   // Blue
-        ((75, 0, 130), 0.80),  // Indigo
-        ((148, 0, 211), 1.0),  // Violet
-    ]
+/// sRGB の成分 (0〜255) を線形の値 (0.0〜1.0) にする
+pub fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// RGB を OKLab (L, a, b) にする。色の近さを人の見え方に近い尺度で比べるのに使う
+pub fn rgb_to_oklab(rgb: (u8, u8, u8)) -> (f32, f32, f32) {
+    let (r, g, b) = (
+        srgb_to_linear(rgb.0),
+        srgb_to_linear(rgb.1),
+        srgb_to_linear(rgb.2),
+    );
+    let l = (0.412_221_47 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    (
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    )
+}
+
+/// 線形の値 (0.0〜1.0) を sRGB の成分 (0〜255) にする。範囲外の値は丸める
+pub fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+/// OKLab (L, a, b) を RGB にする。sRGB で表せない色は各成分を丸める
+pub fn oklab_to_rgb((l, a, b): (f32, f32, f32)) -> (u8, u8, u8) {
+    let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+    (
+        linear_to_srgb(4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_),
+        linear_to_srgb(-1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_),
+        linear_to_srgb(-0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_),
+    )
+}
+
+/// xterm の 16 色パレット (Black ... LightWhite の順)
+pub const ANSI_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// 256 色の番号を xterm の既定パレットでの RGB にする
+pub fn code256_to_rgb(code: u8) -> (u8, u8, u8) {
+    match code {
+        0..=15 => ANSI_PALETTE[code as usize],
+        16..=231 => {
+            let i = code - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            (level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        232..=255 => {
+            let v = 8 + (code - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+/// 色の RGB。名前付きの色は xterm の既定パレットの値にする
+pub fn named_color_to_rgb(color: &NamedColor) -> (u8, u8, u8) {
+    match color {
+        NamedColor::Black => ANSI_PALETTE[0],
+        NamedColor::Red => ANSI_PALETTE[1],
+        NamedColor::Green => ANSI_PALETTE[2],
+        NamedColor::Yellow => ANSI_PALETTE[3],
+        NamedColor::Blue => ANSI_PALETTE[4],
+        NamedColor::Magenta => ANSI_PALETTE[5],
+        NamedColor::Cyan => ANSI_PALETTE[6],
+        NamedColor::White => ANSI_PALETTE[7],
+        NamedColor::LightBlack => ANSI_PALETTE[8],
+        NamedColor::LightRed => ANSI_PALETTE[9],
+        NamedColor::LightGreen => ANSI_PALETTE[10],
+        NamedColor::LightYellow => ANSI_PALETTE[11],
+        NamedColor::LightBlue => ANSI_PALETTE[12],
+        NamedColor::LightMagenta => ANSI_PALETTE[13],
+        NamedColor::LightCyan => ANSI_PALETTE[14],
+        NamedColor::LightWhite => ANSI_PALETTE[15],
+        NamedColor::Code256(code) => code256_to_rgb(*code),
+        NamedColor::FullColor(rgb) => *rgb,
+    }
+}
+
+pub fn create_default_rainbow_gradient() -> Gradient {
+    Gradient::new(vec![
+        GradientStop::new(NamedColor::FullColor((255, 0, 0)), 0.0), // Red
+        GradientStop::new(NamedColor::FullColor((255, 127, 0)), 0.16), // Orange
+        GradientStop::new(NamedColor::FullColor((255, 255, 0)), 0.32), // Yellow
+        GradientStop::new(NamedColor::FullColor((0, 255, 0)), 0.48), // Green
+        GradientStop::new(NamedColor::FullColor((0, 0, 255)), 0.64), // Blue
+        GradientStop::new(NamedColor::FullColor((75, 0, 130)), 0.80), // Indigo
+        GradientStop::new(NamedColor::FullColor((148, 0, 211)), 1.0), // Violet
+    ])
 }
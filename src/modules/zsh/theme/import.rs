@@ -0,0 +1,369 @@
+mod p10k;
+mod starship;
+
+use clap::ValueEnum;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use zsh_seq::NamedColor;
+
+use super::{
+    color_depth::NAMED_16,
+    color_named_color::{color_string, prompts_color},
+    color_parse,
+    prompt_theme::{PromptContent, PromptContents, PromptTheme},
+    share,
+};
+use crate::zsh::prompt::{PromptConnection, PromptSeparation};
+
+/// 取り込み元のプロンプト
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ImportSource {
+    /// starship.toml
+    Starship,
+    /// Powerlevel10k の .p10k.zsh
+    P10k,
+}
+
+/// 変換結果と、変換できなかったものの一覧
+pub struct Imported {
+    pub theme: PromptTheme,
+    pub untranslated: Vec<String>,
+}
+
+pub fn import(from: ImportSource, path: &Path) -> io::Result<Imported> {
+    let content = fs::read_to_string(path)?;
+    match from {
+        ImportSource::Starship => starship::import(&content),
+        ImportSource::P10k => Ok(p10k::import(&content)),
+    }
+}
+
+/// 設定を取り込み、変換できなかったものを報告してから、テーマを表示して確認し保存する。
+/// `out` が無ければ使用中のテーマファイルに保存する。取り消した場合は `None` を返す
+pub fn run(from: ImportSource, path: &Path, out: Option<&Path>) -> io::Result<Option<PathBuf>> {
+    let imported = import(from, path)?;
+    if imported.untranslated.is_empty() {
+        eprintln!("Everything was translated.");
+    } else {
+        eprintln!("Could not translate:");
+        for item in &imported.untranslated {
+            eprintln!("  - {}", item);
+        }
+    }
+    share::confirm_and_save(imported.theme, out)
+}
+
+/// 取り込み元の 1 つの部品が何に当たるか
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Module {
+    User,
+    Host,
+    Dir,
+    Git,
+    Status,
+    Duration,
+    Time,
+    Os,
+}
+
+/// 行ごとに部品を積み上げてテーマを作る
+struct ThemeBuilder {
+    theme: PromptTheme,
+    untranslated: Vec<String>,
+    /// 1 つにまとめた組み込みコマンドの位置 (行, 右側か, 番号)
+    merged: HashMap<&'static str, (usize, bool, usize)>,
+    /// 行ごとに最初に見つかった背景色
+    backgrounds: Vec<Option<NamedColor>>,
+    /// 左右のセパレーター
+    separators: [Option<PromptSeparation>; 2],
+}
+
+impl ThemeBuilder {
+    fn new() -> Self {
+        Self {
+            theme: PromptTheme {
+                prompt_contents_list: vec![empty_line()],
+                ..PromptTheme::default()
+            },
+            untranslated: Vec::new(),
+            merged: HashMap::new(),
+            backgrounds: vec![None],
+            separators: [None, None],
+        }
+    }
+
+    fn untranslated(&mut self, item: impl Into<String>) {
+        let item = item.into();
+        if !self.untranslated.contains(&item) {
+            self.untranslated.push(item);
+        }
+    }
+
+    fn line_count(&self) -> usize {
+        self.theme.prompt_contents_list.len()
+    }
+
+    fn new_line(&mut self) {
+        self.theme.prompt_contents_list.push(empty_line());
+        self.backgrounds.push(None);
+    }
+
+    fn line(&mut self, line: usize) -> &mut PromptContents {
+        while self.line_count() <= line {
+            self.new_line();
+        }
+        &mut self.theme.prompt_contents_list[line]
+    }
+
+    fn last_line(&self) -> usize {
+        self.line_count() - 1
+    }
+
+    fn push(&mut self, line: usize, right: bool, content: PromptContent) {
+        let contents = self.line(line);
+        if right {
+            contents.right.push(content);
+        } else {
+            contents.left.push(content);
+        }
+    }
+
+    fn background(&mut self, line: usize, bg: Option<NamedColor>) {
+        let Some(bg) = bg else {
+            return;
+        };
+        self.line(line);
+        match self.backgrounds[line] {
+            None => self.backgrounds[line] = Some(bg),
+            Some(first) if first != bg => self.untranslated(
+                "per-segment background colours (the first one is used as the line background)",
+            ),
+            Some(_) => {}
+        }
+    }
+
+    /// 部品を追加する。状態と実行時間のように 1 つのコマンドで表示するものはまとめる
+    fn module(
+        &mut self,
+        line: usize,
+        right: bool,
+        module: Module,
+        fg: Option<NamedColor>,
+        bg: Option<NamedColor>,
+    ) {
+        self.background(line, bg);
+        let key = match module {
+            Module::Status | Module::Duration => "cmd",
+            Module::Git => "git",
+            _ => "",
+        };
+        if let Some(&(l, r, i)) = self.merged.get(key) {
+            if module == Module::Duration {
+                let contents = &mut self.theme.prompt_contents_list[l];
+                let side = if r {
+                    &mut contents.right
+                } else {
+                    &mut contents.left
+                };
+                if let PromptContent::BuildIn {
+                    command:
+                        zsh_prompts::Commands::Cmd {
+                            last_command_executed,
+                            ..
+                        },
+                } = &mut side[i]
+                {
+                    *last_command_executed = Some("$LAST_COMMAND_EXECUTED".to_string());
+                }
+            }
+            return;
+        }
+        let content = match module {
+            Module::User => shell("whoami", &[], fg, bg),
+            Module::Host => shell("hostname", &[], fg, bg),
+            Module::Dir => build_in(zsh_prompts::Commands::Pwd {
+                color: fg.map(color_string),
+            }),
+            Module::Git => {
+                let color = fg.map(prompts_color);
+                build_in(zsh_prompts::Commands::Git {
+                    path: None,
+                    options: zsh_prompts::git::GitStatusOptions {
+                        default_color_option: None,
+                        git_icon_color_option: color,
+                        branch_color_option: color,
+                        staged_color_option: None,
+                        unstaged_color_option: None,
+                        untracked_color_option: None,
+                        conflict_color_option: None,
+                        stashed_color_option: None,
+                        clean_color_option: None,
+                        ahead_color_option: None,
+                        behind_color_option: None,
+                    },
+                })
+            }
+            Module::Status | Module::Duration => build_in(zsh_prompts::Commands::Cmd {
+                last_status: "$LAST_STATUS".to_string(),
+                last_command_executed: (module == Module::Duration)
+                    .then(|| "$LAST_COMMAND_EXECUTED".to_string()),
+                color: fg.map(color_string),
+            }),
+            Module::Time => build_in(zsh_prompts::Commands::Time {
+                color: fg.map(color_string),
+            }),
+            Module::Os => build_in(zsh_prompts::Commands::Os {
+                color: fg.map(color_string),
+            }),
+        };
+        if !key.is_empty() {
+            let contents = self.line(line);
+            let index = if right {
+                contents.right.len()
+            } else {
+                contents.left.len()
+            };
+            self.merged.insert(key, (line, right, index));
+        }
+        self.push(line, right, content);
+    }
+
+    /// セパレーターの記号に近い形を左右どちらかのセパレーターにする。
+    /// セパレーターとして扱ったら `true`
+    fn separator(&mut self, right: bool, glyph: &str) -> bool {
+        let Some(separation) = separation_for(glyph.trim()) else {
+            return false;
+        };
+        let side = &mut self.separators[right as usize];
+        if side.is_none() {
+            *side = Some(separation);
+        }
+        true
+    }
+
+    /// 行をつなぐ文字に近いものを選ぶ
+    fn connection(&mut self, symbol: &str) {
+        let symbol = symbol.trim();
+        match PromptConnection::ALL
+            .into_iter()
+            .find(|c| c.to_string() == symbol)
+        {
+            Some(connection) => {
+                for contents in &mut self.theme.prompt_contents_list {
+                    contents.connection = connection;
+                }
+            }
+            None if symbol.is_empty() => {}
+            None => self.untranslated(format!("fill character {:?}", symbol)),
+        }
+    }
+
+    fn finish(mut self) -> Imported {
+        let connection = self
+            .theme
+            .prompt_contents_list
+            .iter()
+            .map(|c| c.connection)
+            .find(|c| *c != PromptConnection::None)
+            .unwrap_or_default();
+        for (contents, bg) in self
+            .theme
+            .prompt_contents_list
+            .iter_mut()
+            .zip(&self.backgrounds)
+        {
+            contents.connection = connection;
+            if let Some(bg) = bg {
+                contents.color.bg = *bg;
+            }
+            for (separators, separation) in [
+                &mut contents.left_segment_separators,
+                &mut contents.right_segment_separators,
+            ]
+            .into_iter()
+            .zip(self.separators)
+            {
+                if let Some(separation) = separation {
+                    separators.start_separator = separation;
+                    separators.mid_separator = separation;
+                    separators.end_separator = separation;
+                }
+            }
+        }
+        // 何も無い末尾の行は取り除く
+        while self.theme.prompt_contents_list.len() > 1
+            && self
+                .theme
+                .prompt_contents_list
+                .last()
+                .is_some_and(|c| c.left.is_empty() && c.right.is_empty())
+        {
+            self.theme.prompt_contents_list.pop();
+        }
+        Imported {
+            theme: self.theme,
+            untranslated: self.untranslated,
+        }
+    }
+}
+
+fn empty_line() -> PromptContents {
+    PromptContents {
+        left: Vec::new(),
+        right: Vec::new(),
+        ..PromptContents::default()
+    }
+}
+
+fn shell(
+    cmd: &str,
+    args: &[&str],
+    fg: Option<NamedColor>,
+    bg: Option<NamedColor>,
+) -> PromptContent {
+    PromptContent::Shell {
+        cmd: cmd.to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        envs: HashMap::new(),
+        fg,
+        bg,
+    }
+}
+
+fn build_in(command: zsh_prompts::Commands) -> PromptContent {
+    PromptContent::BuildIn { command }
+}
+
+fn literal(value: &str, fg: Option<NamedColor>, bg: Option<NamedColor>) -> PromptContent {
+    PromptContent::Literal {
+        value: value.to_string(),
+        fg,
+        bg,
+    }
+}
+
+fn separation_for(glyph: &str) -> Option<PromptSeparation> {
+    PromptSeparation::ALL.into_iter().find(|s| {
+        let (b, l) = (s.sep_box(), s.sep_line());
+        [b.left, b.right, l.left, l.right]
+            .iter()
+            .any(|g| !g.trim().is_empty() && g.trim() == glyph)
+    })
+}
+
+/// Starship / Powerlevel10k で使われる色の書き方を読む。
+/// 0〜15 の番号は `NamedColor` の基本 16 色にする
+pub(super) fn parse_color(s: &str) -> Option<NamedColor> {
+    color_parse::parse(s).ok().map(simplify)
+}
+
+/// `NamedColor` の基本 16 色に当たる番号であれば、その色にする
+fn simplify(color: NamedColor) -> NamedColor {
+    match color {
+        NamedColor::Code256(code) if (code as usize) < NAMED_16.len() => NAMED_16[code as usize],
+        other => other,
+    }
+}
@@ -0,0 +1,193 @@
+use regex::Regex;
+use std::collections::HashMap;
+use zsh_seq::NamedColor;
+
+use super::{Imported, Module, ThemeBuilder, parse_color, shell};
+
+const PREFIX: &str = "POWERLEVEL9K_";
+
+/// `.p10k.zsh` の `typeset -g POWERLEVEL9K_*=...` を読み出したもの
+struct P10kConfig {
+    values: HashMap<String, String>,
+    arrays: HashMap<String, Vec<String>>,
+}
+
+impl P10kConfig {
+    fn parse(content: &str) -> Self {
+        // 配列: NAME=( ... ) 。要素は空白区切りで、# 以降はコメント
+        let array = Regex::new(r"(?s)\bPOWERLEVEL9K_(\w+)=\((.*?)\)").unwrap();
+        // 値: NAME=VALUE (引用符付きも可)
+        let value = Regex::new(
+            r#"\bPOWERLEVEL9K_([\w{},]+)=(\$'(?:[^'\\]|\\.)*'|'[^']*'|"[^"]*"|[^\s(]\S*)"#,
+        )
+        .unwrap();
+
+        let arrays = array
+            .captures_iter(content)
+            .map(|c| {
+                let items = c[2]
+                    .lines()
+                    .map(|line| line.split('#').next().unwrap_or(""))
+                    .flat_map(str::split_whitespace)
+                    .map(unquote)
+                    .collect();
+                (c[1].to_string(), items)
+            })
+            .collect();
+        let values = value
+            .captures_iter(content)
+            .flat_map(|c| {
+                let value = unquote(&c[2]);
+                expand_braces(&c[1])
+                    .into_iter()
+                    .map(move |name| (name, value.clone()))
+            })
+            .collect();
+        Self { values, arrays }
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    fn color(&self, name: &str) -> Option<NamedColor> {
+        self.value(name).and_then(parse_color)
+    }
+
+    /// 要素の色。`DIR_FOREGROUND` のように要素名を大文字にした設定を見る
+    fn element_style(&self, element: &str) -> (Option<NamedColor>, Option<NamedColor>) {
+        let element = element.to_ascii_uppercase();
+        (
+            self.color(&format!("{}_FOREGROUND", element)),
+            self.color(&format!("{}_BACKGROUND", element))
+                .or_else(|| self.color("BACKGROUND")),
+        )
+    }
+}
+
+/// `PROMPT_CHAR_OK_{VIINS,VICMD}_FOREGROUND` のような波括弧展開を行う
+fn expand_braces(name: &str) -> Vec<String> {
+    let (Some(open), Some(close)) = (name.find('{'), name.find('}')) else {
+        return vec![name.to_string()];
+    };
+    if close < open {
+        return vec![name.to_string()];
+    }
+    let (head, tail) = (&name[..open], &name[close + 1..]);
+    name[open + 1..close]
+        .split(',')
+        .flat_map(|part| expand_braces(&format!("{}{}{}", head, part, tail)))
+        .collect()
+}
+
+/// zsh の引用符と `$''` 形式のエスケープを外す
+fn unquote(s: &str) -> String {
+    if let Some(inner) = s.strip_prefix("$'").and_then(|s| s.strip_suffix('\'')) {
+        let mut out = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        out.push(c);
+                    }
+                }
+                Some('n') => out.push('\n'),
+                Some(c) => out.push(c),
+                None => {}
+            }
+        }
+        return out;
+    }
+    s.trim_matches(|c| c == '\'' || c == '"').to_string()
+}
+
+pub fn import(content: &str) -> Imported {
+    let config = P10kConfig::parse(content);
+    let mut builder = ThemeBuilder::new();
+    if config.arrays.is_empty() && config.values.is_empty() {
+        builder.untranslated(format!("no {}* settings were found", PREFIX));
+    }
+
+    let left = config
+        .arrays
+        .get("LEFT_PROMPT_ELEMENTS")
+        .cloned()
+        .unwrap_or_default();
+    let right = config
+        .arrays
+        .get("RIGHT_PROMPT_ELEMENTS")
+        .cloned()
+        .unwrap_or_default();
+    for (elements, is_right) in [(left, false), (right, true)] {
+        let mut line = 0;
+        for element in &elements {
+            if element == "newline" {
+                line += 1;
+                continue;
+            }
+            let (fg, bg) = config.element_style(element);
+            let module = match element.as_str() {
+                "os_icon" => Module::Os,
+                "dir" => Module::Dir,
+                "vcs" => Module::Git,
+                "status" => Module::Status,
+                "command_execution_time" => Module::Duration,
+                "time" => Module::Time,
+                "context" => {
+                    // user@host
+                    builder.background(line, bg);
+                    builder.push(line, is_right, shell("whoami", &[], fg, bg));
+                    builder.module(line, is_right, Module::Host, fg, bg);
+                    continue;
+                }
+                "prompt_char" => {
+                    let transient = &mut builder.theme.transient_color;
+                    if let Some(ok) = config.color("PROMPT_CHAR_OK_VIINS_FOREGROUND") {
+                        transient.pc = ok;
+                    }
+                    if let Some(error) = config.color("PROMPT_CHAR_ERROR_VIINS_FOREGROUND") {
+                        transient.sc = error;
+                    }
+                    continue;
+                }
+                other => {
+                    builder.untranslated(format!("element `{}`", other));
+                    continue;
+                }
+            };
+            builder.module(line, is_right, module, fg, bg);
+        }
+    }
+
+    for (name, right) in [
+        ("LEFT_SEGMENT_SEPARATOR", false),
+        ("LEFT_SUBSEGMENT_SEPARATOR", false),
+        ("LEFT_PROMPT_LAST_SEGMENT_END_SYMBOL", false),
+        ("RIGHT_SEGMENT_SEPARATOR", true),
+        ("RIGHT_SUBSEGMENT_SEPARATOR", true),
+        ("RIGHT_PROMPT_FIRST_SEGMENT_START_SYMBOL", true),
+    ] {
+        if let Some(glyph) = config.value(name)
+            && !glyph.trim().is_empty()
+            && !builder.separator(right, glyph)
+        {
+            builder.untranslated(format!("{}{} {:?}", PREFIX, name, glyph));
+        }
+    }
+    if let Some(gap) = config.value("MULTILINE_FIRST_PROMPT_GAP_CHAR") {
+        builder.connection(gap);
+    }
+    if config.value("TRANSIENT_PROMPT").is_some_and(|v| v == "off") {
+        builder.untranslated(format!(
+            "{}TRANSIENT_PROMPT=off (zsh-infinite always uses a transient prompt)",
+            PREFIX
+        ));
+    }
+    builder.finish()
+}
@@ -0,0 +1,312 @@
+use std::{collections::HashMap, io};
+use toml::{Table, Value};
+use zsh_seq::NamedColor;
+
+use super::{Imported, Module, ThemeBuilder, literal, parse_color, shell};
+
+/// `format` が無い場合の Starship の既定値
+const DEFAULT_FORMAT: &str = "$all";
+/// `$all` のうち、対応するものがあるモジュール
+const ALL_MODULES: &str =
+    "$username$hostname$directory$git_branch$git_status$cmd_duration$line_break$character";
+
+/// 書式文字列の要素
+#[derive(Debug)]
+enum Token {
+    Var(String),
+    Text(String),
+    /// `[中身](スタイル)`
+    Styled(Vec<Token>, String),
+    /// `(中身)`。中の変数が空なら表示されない
+    Conditional(Vec<Token>),
+}
+
+/// 前景色と背景色
+type Style = (Option<NamedColor>, Option<NamedColor>);
+
+struct Importer<'a> {
+    config: &'a Table,
+    palette: HashMap<String, String>,
+    builder: ThemeBuilder,
+}
+
+pub fn import(content: &str) -> io::Result<Imported> {
+    let config: Table = toml::from_str(content).map_err(io::Error::other)?;
+    let palette = config
+        .get("palette")
+        .and_then(Value::as_str)
+        .and_then(|name| config.get("palettes")?.get(name)?.as_table())
+        .map(|palette| {
+            palette
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut importer = Importer {
+        config: &config,
+        palette,
+        builder: ThemeBuilder::new(),
+    };
+
+    let format = config
+        .get("format")
+        .and_then(Value::as_str)
+        .unwrap_or(DEFAULT_FORMAT);
+    importer.walk(&parse(format), false, (None, None));
+    if let Some(right_format) = config.get("right_format").and_then(Value::as_str) {
+        importer.walk(&parse(right_format), true, (None, None));
+    }
+    if config.contains_key("continuation_prompt") {
+        importer.builder.untranslated("continuation_prompt");
+    }
+    Ok(importer.builder.finish())
+}
+
+impl<'a> Importer<'a> {
+    fn module_table(&self, name: &str) -> Option<&'a Table> {
+        let mut table = self.config;
+        for part in name.split('.') {
+            table = table.get(part)?.as_table()?;
+        }
+        Some(table)
+    }
+
+    fn module_str(&self, name: &str, key: &str) -> Option<&'a str> {
+        self.module_table(name)?.get(key)?.as_str()
+    }
+
+    /// Starship で既定で無効になっているモジュール
+    fn is_disabled(&self, name: &str) -> bool {
+        let default = matches!(name, "time" | "status" | "os");
+        self.module_table(name)
+            .and_then(|table| table.get("disabled"))
+            .and_then(Value::as_bool)
+            .unwrap_or(default)
+    }
+
+    fn color(&self, name: &str) -> Option<NamedColor> {
+        let name = self.palette.get(name).map(String::as_str).unwrap_or(name);
+        parse_color(name)
+    }
+
+    /// `bold fg:#ffffff bg:blue` のようなスタイルを読む
+    fn style(&self, style: &str) -> Style {
+        let (mut fg, mut bg) = (None, None);
+        for word in style.split_whitespace() {
+            if let Some(color) = word.strip_prefix("fg:") {
+                fg = self.color(color);
+            } else if let Some(color) = word.strip_prefix("bg:") {
+                bg = self.color(color);
+            } else if let Some(color) = self.color(word) {
+                fg = Some(color);
+            }
+        }
+        (fg, bg)
+    }
+
+    fn module_style(&self, name: &str) -> Style {
+        let key = if name == "username" {
+            "style_user"
+        } else {
+            "style"
+        };
+        let default = match name {
+            "username" => "bold yellow",
+            "hostname" => "bold dimmed green",
+            "directory" => "bold cyan",
+            "git_branch" => "bold purple",
+            "git_status" | "status" => "bold red",
+            "cmd_duration" | "time" => "bold yellow",
+            "os" => "bold white",
+            _ => "",
+        };
+        self.style(self.module_str(name, key).unwrap_or(default))
+    }
+
+    fn walk(&mut self, tokens: &[Token], right: bool, style: Style) {
+        for token in tokens {
+            match token {
+                Token::Var(name) => self.var(name, right, style),
+                Token::Text(text) => self.text(text, right, style),
+                Token::Styled(inner, inner_style) => {
+                    let (fg, bg) = self.style(inner_style);
+                    self.walk(inner, right, (fg.or(style.0), bg.or(style.1)));
+                }
+                Token::Conditional(inner) => self.walk(inner, right, style),
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str, right: bool, (fg, bg): Style) {
+        if text.trim().is_empty() || self.builder.separator(right, text) {
+            return;
+        }
+        let line = self.builder.last_line();
+        self.builder.push(line, right, literal(text, fg, bg));
+    }
+
+    fn var(&mut self, name: &str, right: bool, style: Style) {
+        let line = self.builder.last_line();
+        match name {
+            "all" => self.walk(&parse(ALL_MODULES), right, style),
+            "line_break" if !right => self.builder.new_line(),
+            "line_break" => {}
+            "fill" => {
+                let symbol = self.module_str("fill", "symbol").unwrap_or(".");
+                self.builder.connection(symbol);
+            }
+            "character" => self.character(),
+            _ if self.is_disabled(name) => {}
+            _ => {
+                let (fg, bg) = self.module_style(name);
+                let (fg, bg) = (fg.or(style.0), bg.or(style.1));
+                let module = match name {
+                    "username" => Module::User,
+                    "hostname" => Module::Host,
+                    "directory" => Module::Dir,
+                    "git_branch" | "git_commit" | "git_state" | "git_status" | "git_metrics" => {
+                        Module::Git
+                    }
+                    "cmd_duration" => Module::Duration,
+                    "status" => Module::Status,
+                    "time" => Module::Time,
+                    "os" => Module::Os,
+                    _ => {
+                        self.other(name, line, right, (fg, bg));
+                        return;
+                    }
+                };
+                self.builder.module(line, right, module, fg, bg);
+            }
+        }
+    }
+
+    /// 対応する組み込みコマンドの無いモジュール。コマンドで表せるものはコマンドにする
+    fn other(&mut self, name: &str, line: usize, right: bool, (fg, bg): Style) {
+        if let Some(custom) = name.strip_prefix("custom.") {
+            let Some(command) = self.module_str(name, "command") else {
+                self.builder
+                    .untranslated(format!("custom.{} (no `command`)", custom));
+                return;
+            };
+            if self
+                .module_table(name)
+                .is_some_and(|t| t.contains_key("when"))
+            {
+                self.builder.untranslated(format!(
+                    "custom.{}: `when` condition (the segment is always shown)",
+                    custom
+                ));
+            }
+            self.builder.background(line, bg);
+            self.builder
+                .push(line, right, shell("sh", &["-c", command], fg, bg));
+        } else if let Some(variable) = name
+            .strip_prefix("env_var.")
+            .or_else(|| (name == "env_var").then(|| self.module_str(name, "variable"))?)
+        {
+            self.builder.background(line, bg);
+            self.builder
+                .push(line, right, shell("printenv", &[variable], fg, bg));
+        } else {
+            self.builder.untranslated(format!("module `{}`", name));
+        }
+    }
+
+    /// プロンプト記号の色を一時プロンプトの色にする
+    fn character(&mut self) {
+        for (key, default) in [
+            ("success_symbol", "[❯](bold green)"),
+            ("error_symbol", "[❯](bold red)"),
+        ] {
+            let symbol = self.module_str("character", key).unwrap_or(default);
+            let Some(Token::Styled(_, style)) = parse(symbol)
+                .into_iter()
+                .find(|t| matches!(t, Token::Styled(..)))
+            else {
+                continue;
+            };
+            if let (Some(fg), _) = self.style(&style) {
+                let transient = &mut self.builder.theme.transient_color;
+                if key == "success_symbol" {
+                    transient.pc = fg;
+                } else {
+                    transient.sc = fg;
+                }
+            }
+        }
+    }
+}
+
+fn parse(format: &str) -> Vec<Token> {
+    let chars: Vec<char> = format.chars().collect();
+    let mut i = 0;
+    parse_until(&chars, &mut i, None)
+}
+
+fn parse_until(chars: &[char], i: &mut usize, end: Option<char>) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let flush = |text: &mut String, tokens: &mut Vec<Token>| {
+        if !text.is_empty() {
+            tokens.push(Token::Text(std::mem::take(text)));
+        }
+    };
+    while *i < chars.len() {
+        let c = chars[*i];
+        *i += 1;
+        match c {
+            '\\' if *i < chars.len() => {
+                text.push(chars[*i]);
+                *i += 1;
+            }
+            _ if Some(c) == end => break,
+            '$' => {
+                flush(&mut text, &mut tokens);
+                let name = if chars.get(*i) == Some(&'{') {
+                    *i += 1;
+                    take_while(chars, i, |c| c != '}')
+                } else {
+                    take_while(chars, i, |c| c.is_ascii_alphanumeric() || c == '_')
+                };
+                if chars.get(*i) == Some(&'}') {
+                    *i += 1;
+                }
+                tokens.push(Token::Var(name));
+            }
+            '[' => {
+                flush(&mut text, &mut tokens);
+                let inner = parse_until(chars, i, Some(']'));
+                let style = if chars.get(*i) == Some(&'(') {
+                    *i += 1;
+                    let style = take_while(chars, i, |c| c != ')');
+                    *i += 1;
+                    style
+                } else {
+                    String::new()
+                };
+                tokens.push(Token::Styled(inner, style));
+            }
+            '(' => {
+                flush(&mut text, &mut tokens);
+                tokens.push(Token::Conditional(parse_until(chars, i, Some(')'))));
+            }
+            '\n' => {
+                flush(&mut text, &mut tokens);
+                tokens.push(Token::Var("line_break".to_string()));
+            }
+            _ => text.push(c),
+        }
+    }
+    flush(&mut text, &mut tokens);
+    tokens
+}
+
+fn take_while(chars: &[char], i: &mut usize, f: impl Fn(char) -> bool) -> String {
+    let start = *i;
+    while *i < chars.len() && f(chars[*i]) {
+        *i += 1;
+    }
+    chars[start..*i].iter().collect()
+}
@@ -0,0 +1,81 @@
+use serde_yaml::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use zsh_seq::NamedColor;
+
+use super::{
+    extends,
+    manager::ThemeFormat,
+    migrate, palette,
+    prompt_theme::{PromptContent, PromptTheme},
+};
+
+/// ディレクトリごとの上書き設定のファイル名
+pub const LOCAL_THEME_FILE: &str = ".zsh-infinite.yaml";
+
+/// `cwd` から親ディレクトリへ遡り、最初に見つかった上書き設定ファイルを返す
+pub fn find_local_theme(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .map(|dir| dir.join(LOCAL_THEME_FILE))
+        .find(|path| path.is_file())
+}
+
+/// `theme` の上に上書き設定ファイルの内容を重ねる
+///
+/// マージの規則は `extends` と同じ。上書き設定ファイル自体の `extends` は無視する。
+pub fn apply_local_theme(theme: &PromptTheme, path: &Path) -> Result<PromptTheme, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let format = ThemeFormat::Yaml;
+    let overrides: Value = format
+        .parse(&content)
+        .map_err(|e| format!("Failed to parse {:?}: {}", path, e))?;
+    if overrides.is_null() {
+        // 空のファイルは何も上書きしない
+        return Ok(theme.clone());
+    }
+    let mut overrides = migrate::migrate(overrides, format)
+        .map_err(|e| format!("Failed to migrate {:?}: {}", path, e))?
+        .value;
+    match &mut overrides {
+        Value::Mapping(map) => {
+            map.remove("extends");
+        }
+        _ => return Err(format!("{:?} must contain a mapping", path)),
+    }
+
+    let mut base = format.to_value(theme)?;
+    if let Value::Mapping(map) = &mut base {
+        map.remove("extends");
+    }
+    // パレットを上書きした場合も元のテーマの参照に反映する
+    palette::unresolve(&mut base, &theme.palette_references);
+    let mut merged = extends::merge(base, overrides, false);
+    let references =
+        palette::resolve(&mut merged, format).map_err(|e| format!("{:?}: {}", path, e))?;
+    let mut merged: PromptTheme = format
+        .from_value(merged)
+        .map_err(|e| format!("Failed to deserialize {:?}: {}", path, e))?;
+    merged.palette_references = references;
+    Ok(merged)
+}
+
+/// 信頼されていない上書き設定ファイルがある場合のテーマ。
+/// 設定は読まずに、1 行目の左端に警告を表示する
+pub fn untrusted_theme(theme: &PromptTheme) -> PromptTheme {
+    let mut theme = theme.clone();
+    if let Some(contents) = theme.prompt_contents_list.first_mut() {
+        contents.left.insert(
+            0,
+            PromptContent::Literal {
+                value: "⚠ untrusted".to_string(),
+                fg: Some(NamedColor::Yellow),
+                bg: None,
+            },
+        );
+    }
+    theme
+}
@@ -1,21 +1,243 @@
+use clap::ValueEnum;
 use directories::ProjectDirs;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_yaml::Value;
 use std::{
-    fs,
+    env, fs,
     io::{self, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::Mutex,
 };
 
-use crate::zsh::theme::prompt_theme::PromptTheme;
+use crate::zsh::theme::{extends, migrate, palette, profile, prompt_theme::PromptTheme};
 
 const QUALIFIER: &str = "org";
 const ORGANIZATION: &str = "infinite";
 const APPLICATION: &str = "zsh-infinite";
-const THEME_FILE_NAME: &str = "theme.yaml";
+const THEME_FILE_STEM: &str = "theme";
+/// テーマファイルのパスを指定する環境変数
+pub const CONFIG_ENV: &str = "ZSH_INFINITE_CONFIG";
 
-fn get_theme_file_path() -> Option<PathBuf> {
-    if let Some(proj_dirs) = ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION) {
-        let config_dir = proj_dirs.config_dir();
-        let theme_file_path = config_dir.join(THEME_FILE_NAME);
+/// `--config` などで指定されたテーマファイルのパス。環境変数より優先する
+static CONFIG_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// テーマファイルの形式。拡張子から判別する
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ThemeFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ThemeFormat {
+    /// 設定ディレクトリでテーマファイルを探す順序
+    pub const ALL: [Self; 3] = [Self::Yaml, Self::Toml, Self::Json];
+
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::Toml,
+            Some("json") => Self::Json,
+            _ => Self::Yaml,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self, content: &str) -> Result<T, String> {
+        match self {
+            Self::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+            Self::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            Self::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<String, String> {
+        match self {
+            Self::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+            Self::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+            Self::Json => serde_json::to_string_pretty(value)
+                .map(|s| s + "\n")
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// この形式での表現のまま値を `Value` に変換する。
+    /// YAML では enum が `!tag` に、TOML/JSON では `{tag: 中身}` になる
+    pub fn to_value<T: Serialize>(&self, value: &T) -> Result<Value, String> {
+        match self {
+            Self::Yaml => serde_yaml::to_value(value).map_err(|e| e.to_string()),
+            Self::Toml | Self::Json => serde_json::to_value(value)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_yaml::to_value(json).map_err(|e| e.to_string())),
+        }
+    }
+
+    pub fn from_value<T: DeserializeOwned>(&self, value: Value) -> Result<T, String> {
+        match self {
+            Self::Yaml => serde_yaml::from_value(value).map_err(|e| e.to_string()),
+            Self::Toml | Self::Json => {
+                serde_json::from_value(to_json(&value)).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    pub fn serialize_value(&self, value: &Value) -> Result<String, String> {
+        match self {
+            Self::Yaml => self.serialize(value),
+            // TOML には null が無いため、省略して None として読ませる
+            Self::Toml => self.serialize(&strip_nulls(to_json(value))),
+            Self::Json => self.serialize(&to_json(value)),
+        }
+    }
+}
+
+/// `Value` を JSON に変換する。YAML のタグは `{tag: 中身}` 形式にする
+pub fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::Number(n) => serde_json::to_value(n).unwrap_or(serde_json::Value::Null),
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        Value::Sequence(items) => serde_json::Value::Array(items.iter().map(to_json).collect()),
+        Value::Mapping(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let key = k
+                        .as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| format!("{:?}", k));
+                    (key, to_json(v))
+                })
+                .collect(),
+        ),
+        Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string();
+            let mut object = serde_json::Map::new();
+            object.insert(
+                tag.trim_start_matches('!').to_string(),
+                to_json(&tagged.value),
+            );
+            serde_json::Value::Object(object)
+        }
+    }
+}
+
+fn strip_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Array(items) => serde_json::Value::Array(
+            items
+                .into_iter()
+                .filter(|v| !v.is_null())
+                .map(strip_nulls)
+                .collect(),
+        ),
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, strip_nulls(v)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// テーマファイルの内容を解釈する。`extends` があれば継承元とマージする
+pub fn parse_theme(content: &str, path: &Path) -> Result<PromptTheme, String> {
+    parse_theme_migrated(content, path).map(|(theme, _)| theme)
+}
+
+/// テーマを読み込む。古いレイアウトから変換した場合は元の版も返す
+fn parse_theme_migrated(content: &str, path: &Path) -> Result<(PromptTheme, Option<u32>), String> {
+    let format = ThemeFormat::from_path(path);
+    let value = format.parse(content)?;
+    let migrated = migrate::migrate(value, format)?;
+    let mut value = extends::resolve(migrated.value, format, path)?;
+    let references = palette::resolve(&mut value, format)?;
+    let from = migrated.changed.then(|| migrated.from.unwrap_or(0));
+    let mut theme: PromptTheme = format.from_value(value)?;
+    theme.palette_references = references;
+    Ok((theme, from))
+}
+
+pub fn get_config_dir() -> Option<PathBuf> {
+    ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION)
+        .map(|proj_dirs| proj_dirs.config_dir().to_path_buf())
+}
+
+/// `dir` 内の `{stem}.yaml` / `{stem}.toml` / `{stem}.json` のうち、最初に見つかったものを返す
+pub fn find_theme_file(dir: &Path, stem: &str) -> Option<PathBuf> {
+    ThemeFormat::ALL
+        .iter()
+        .map(|format| dir.join(format!("{}.{}", stem, format.extension())))
+        .find(|path| path.exists())
+}
+
+/// テーマファイルのパスを指定する。`None` で指定を解除する
+pub fn set_config_path(path: Option<PathBuf>) {
+    *CONFIG_OVERRIDE.lock().unwrap() = path;
+}
+
+/// `ZSH_INFINITE_CONFIG` の値をテーマファイルのパスにする。
+/// `~` や `$VAR` を展開し、相対パスはホームディレクトリを基準にする。
+/// プロンプトは描画のたびに作業ディレクトリが変わるので、作業ディレクトリは基準にしない
+pub fn config_path_from_env(value: &str) -> Option<PathBuf> {
+    if value.is_empty() {
+        return None;
+    }
+    let expanded = shellexpand::full(value)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| value.to_string());
+    let path = PathBuf::from(expanded);
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        env::home_dir().map(|home| home.join(path))
+    }
+}
+
+/// 指定されたテーマファイルのパス。`set_config_path` の指定、`ZSH_INFINITE_CONFIG` の順に見る
+fn config_path() -> Option<PathBuf> {
+    let path = CONFIG_OVERRIDE.lock().unwrap().clone().or_else(|| {
+        env::var(CONFIG_ENV)
+            .ok()
+            .and_then(|path| config_path_from_env(&path))
+    })?;
+    // `--config` の相対パスは作業ディレクトリを基準にする
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        env::current_dir().ok().map(|cwd| cwd.join(path))
+    }
+}
+
+/// 使用中のテーマファイルのパス。
+/// `--config` か `ZSH_INFINITE_CONFIG` で指定されていればそのファイル、
+/// プロファイルが選択されていればそのファイル、なければ設定ディレクトリ内の
+/// theme.yaml / theme.toml / theme.json のうち最初に見つかったもの (どれも無ければ theme.yaml)
+pub fn get_theme_file_path() -> Option<PathBuf> {
+    if let Some(path) = config_path() {
+        return Some(path);
+    }
+    if let Some(config_dir) = get_config_dir() {
+        if let Some(name) = profile::active_profile() {
+            match profile::profile_path(&name) {
+                Some(path) => return Some(path),
+                None => eprintln!("Active theme profile {:?} not found.", name),
+            }
+        }
+        let theme_file_path = find_theme_file(&config_dir, THEME_FILE_STEM).unwrap_or_else(|| {
+            config_dir.join(format!(
+                "{}.{}",
+                THEME_FILE_STEM,
+                ThemeFormat::Yaml.extension()
+            ))
+        });
         Some(theme_file_path)
     } else {
         None
@@ -26,9 +248,12 @@ pub fn load_theme() -> PromptTheme {
     if let Some(theme_file_path) = get_theme_file_path() {
         if theme_file_path.exists() {
             match fs::read_to_string(&theme_file_path) {
-                Ok(content) => match serde_yaml::from_str(&content) {
-                    Ok(theme) => {
+                Ok(content) => match parse_theme_migrated(&content, &theme_file_path) {
+                    Ok((theme, from)) => {
                         eprintln!("Theme loaded successfully from: {:?}", theme_file_path);
+                        if let Some(from) = from {
+                            upgrade_theme_file(&theme, &theme_file_path, from);
+                        }
                         theme
                     }
                     Err(e) => {
@@ -36,6 +261,7 @@ pub fn load_theme() -> PromptTheme {
                             "Failed to deserialize theme from {:?}: {}",
                             theme_file_path, e
                         );
+                        eprintln!("Run `zsh-infinite theme check` for details.");
                         PromptTheme::default()
                     }
                 },
@@ -61,22 +287,147 @@ pub fn load_theme() -> PromptTheme {
     }
 }
 
+/// `path` のテーマを読み込む。`None` なら使用中のテーマを読み込む。
+/// `load_theme` と違い、読めないテーマは既定のテーマで代用せずにエラーにする
+pub fn load_theme_from(path: Option<&Path>) -> io::Result<PromptTheme> {
+    let active = match path {
+        Some(_) => None,
+        None => get_theme_file_path().filter(|path| path.exists()),
+    };
+    let Some(path) = path.or(active.as_deref()) else {
+        // 使用中のテーマファイルがまだ無ければ既定のテーマを保存して使う
+        return Ok(load_theme());
+    };
+    let content = fs::read_to_string(path)?;
+    parse_theme(&content, path)
+        .map_err(|e| io::Error::other(format!("Failed to deserialize {:?}: {}", path, e)))
+}
+
 pub fn save_theme(theme: &PromptTheme) -> io::Result<()> {
     if let Some(theme_file_path) = get_theme_file_path() {
-        let config_dir = theme_file_path
-            .parent()
-            .expect("Theme file path should have a parent directory");
-        fs::create_dir_all(config_dir)?;
-
-        let content = serde_yaml::to_string(theme).map_err(|e| io::Error::other(e.to_string()))?;
-
-        let mut file = fs::File::create(&theme_file_path)?;
-        file.write_all(content.as_bytes())?;
-        eprintln!("Theme saved successfully to: {:?}", theme_file_path);
-        Ok(())
+        save_theme_to(theme, &theme_file_path)
     } else {
         Err(io::Error::other(
             "Could not determine project directories to save theme file.",
         ))
     }
 }
+
+pub fn save_theme_to(theme: &PromptTheme, theme_file_path: &Path) -> io::Result<()> {
+    let config_dir = theme_file_path
+        .parent()
+        .expect("Theme file path should have a parent directory");
+    fs::create_dir_all(config_dir)?;
+
+    let content = serialize_theme(theme, theme_file_path).map_err(io::Error::other)?;
+
+    let mut file = fs::File::create(theme_file_path)?;
+    file.write_all(content.as_bytes())?;
+    eprintln!("Theme saved successfully to: {:?}", theme_file_path);
+    Ok(())
+}
+
+/// 古いレイアウトのテーマファイルを現在のレイアウトで書き直す。元のファイルは `.bak` を付けて残す
+fn upgrade_theme_file(theme: &PromptTheme, path: &Path, from: u32) {
+    match write_with_backup(theme, path) {
+        Ok(backup) => eprintln!(
+            "Theme file {:?} upgraded from version {} to {}. The original was kept as {:?}.",
+            path,
+            from,
+            migrate::CURRENT_VERSION,
+            backup
+        ),
+        Err(e) => eprintln!(
+            "Theme file {:?} uses an old layout (version {}) and could not be upgraded: {}",
+            path, from, e
+        ),
+    }
+}
+
+/// 元のファイルを `.bak` を付けて残してから `theme` を書き出し、バックアップのパスを返す
+fn write_with_backup(theme: &PromptTheme, path: &Path) -> io::Result<PathBuf> {
+    let content = serialize_theme(theme, path).map_err(io::Error::other)?;
+    let mut backup = path.to_path_buf().into_os_string();
+    backup.push(".bak");
+    let backup = PathBuf::from(backup);
+    fs::copy(path, &backup)?;
+    fs::write(path, content)?;
+    Ok(backup)
+}
+
+/// テーマファイルを現在のレイアウトに変換する。
+/// 変換した場合はバックアップのパスを、既に最新なら `None` を返す
+pub fn migrate_theme(path: Option<&Path>) -> io::Result<Option<PathBuf>> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => get_theme_file_path().ok_or_else(|| {
+            io::Error::other("Could not determine project directories for theme file.")
+        })?,
+    };
+    let content = fs::read_to_string(&path)?;
+    let format = ThemeFormat::from_path(&path);
+    let value: Value = format.parse(&content).map_err(io::Error::other)?;
+    let version = value.get("version").and_then(Value::as_u64);
+    if version == Some(migrate::CURRENT_VERSION as u64) {
+        return Ok(None);
+    }
+    let theme = parse_theme(&content, &path)
+        .map_err(|e| io::Error::other(format!("Failed to deserialize {:?}: {}", path, e)))?;
+    write_with_backup(&theme, &path).map(Some)
+}
+
+/// テーマを書き出す内容を作る。`extends` があれば継承元との差分だけを書き出す
+fn serialize_theme(theme: &PromptTheme, path: &Path) -> Result<String, String> {
+    let format = ThemeFormat::from_path(path);
+    // パレットと同じ色は `$name` の参照として書き出す
+    let mut value = format.to_value(theme)?;
+    palette::unresolve(&mut value, &theme.palette_references);
+    let Some(base) = theme.extends.as_deref() else {
+        return format.serialize_value(&value);
+    };
+    match extends::load_base(base, path) {
+        Ok(mut base_theme) => {
+            base_theme.extends = None;
+            // 差分にも常に version を書く
+            base_theme.version = 0;
+            let mut base_value = format.to_value(&base_theme)?;
+            palette::unresolve(&mut base_value, &base_theme.palette_references);
+            let overrides = extends::diff(&base_value, &value);
+            format.serialize_value(&overrides)
+        }
+        Err(e) => {
+            eprintln!("Failed to resolve `extends: {}`: {}", base, e);
+            format.serialize_value(&value)
+        }
+    }
+}
+
+/// テーマファイルを別の形式に変換する。元のファイルは `.bak` を付けて残し、新しいファイルのパスを返す
+pub fn convert_theme(path: Option<&Path>, to: ThemeFormat) -> io::Result<PathBuf> {
+    let source = match path {
+        Some(path) => path.to_path_buf(),
+        None => get_theme_file_path().ok_or_else(|| {
+            io::Error::other("Could not determine project directories for theme file.")
+        })?,
+    };
+    let content = fs::read_to_string(&source)?;
+    let theme = parse_theme(&content, &source)
+        .map_err(|e| io::Error::other(format!("Failed to deserialize {:?}: {}", source, e)))?;
+
+    let target = source.with_extension(to.extension());
+    // 別のテーマファイルを黙って上書きしない
+    if target != source && target.exists() {
+        return Err(io::Error::other(format!(
+            "{:?} already exists; move it away before converting",
+            target
+        )));
+    }
+    let content = serialize_theme(&theme, &target).map_err(io::Error::other)?;
+    if target != source {
+        let mut backup = source.clone().into_os_string();
+        backup.push(".bak");
+        fs::rename(&source, &backup)?;
+    }
+    fs::write(&target, content)?;
+    Ok(target)
+}
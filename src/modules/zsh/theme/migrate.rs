@@ -0,0 +1,183 @@
+use serde_yaml::{
+    Mapping, Value,
+    value::{Tag, TaggedValue},
+};
+
+use super::manager::ThemeFormat;
+
+const VERSION_KEY: &str = "version";
+
+/// 1 つ前のレイアウトから次のレイアウトへ変換する関数
+type Migration = fn(&mut Value, ThemeFormat);
+
+/// `MIGRATIONS[n]` は version n のレイアウトを version n + 1 に変換する。
+/// `version` の無いファイルは version 0 として扱う
+///
+/// - 0: `- cmd: zsh` / `args: [...]` のようにタグの無いコマンド
+/// - 1: `- build_in: !Os` のように `build_in` キーで組み込みコマンドを指定
+/// - 2: `!Shell` / `!BuildIn` タグ (現在のレイアウト)
+const MIGRATIONS: [Migration; 2] = [tag_shell_commands, tag_build_in_commands];
+
+pub const CURRENT_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// `migrate` の結果
+pub struct Migrated {
+    pub value: Value,
+    /// 元のファイルの `version`。書かれていなければ `None`
+    pub from: Option<u32>,
+    /// `version` 以外に書き換えた箇所があるか
+    pub changed: bool,
+}
+
+/// 読み込んだテーマの値を現在のレイアウトに変換し、`version` を現在の版にする
+pub fn migrate(mut value: Value, format: ThemeFormat) -> Result<Migrated, String> {
+    let Value::Mapping(map) = &mut value else {
+        // マッピングでなければ変換できない。エラーは読み込み側で報告する
+        return Ok(Migrated {
+            value,
+            from: Some(CURRENT_VERSION),
+            changed: false,
+        });
+    };
+    let from = match map.remove(VERSION_KEY) {
+        None => None,
+        Some(version) => Some(
+            version
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| "`version` must be a non-negative integer".to_string())?,
+        ),
+    };
+    let version = from.unwrap_or(0);
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "theme version {} is newer than this zsh-infinite supports ({}); please update zsh-infinite",
+            version, CURRENT_VERSION
+        ));
+    }
+
+    let original = value.clone();
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut value, format);
+    }
+    let changed = value != original;
+    if let Value::Mapping(map) = &mut value {
+        map.insert(VERSION_KEY.into(), CURRENT_VERSION.into());
+    }
+    Ok(Migrated {
+        value,
+        from,
+        changed,
+    })
+}
+
+/// 全ての行の left / right の各要素に `f` を適用する
+fn for_each_content(value: &mut Value, mut f: impl FnMut(&mut Value)) {
+    let Some(lines) = value
+        .get_mut("prompt_contents_list")
+        .and_then(Value::as_sequence_mut)
+    else {
+        return;
+    };
+    for line in lines {
+        for side in ["left", "right"] {
+            if let Some(contents) = line.get_mut(side).and_then(Value::as_sequence_mut) {
+                contents.iter_mut().for_each(&mut f);
+            }
+        }
+    }
+}
+
+/// 形式に合わせて enum の値を作る。YAML では `!tag`、TOML/JSON では `{tag: 中身}`
+fn tagged(format: ThemeFormat, tag: &str, value: Value) -> Value {
+    match format {
+        ThemeFormat::Yaml => Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag),
+            value,
+        })),
+        ThemeFormat::Toml | ThemeFormat::Json => {
+            let mut map = Mapping::new();
+            map.insert(tag.into(), value);
+            Value::Mapping(map)
+        }
+    }
+}
+
+/// enum の値のタグ名
+fn tag_of(value: &Value) -> Option<String> {
+    match value {
+        Value::Tagged(tagged) => Some(tagged.tag.to_string().trim_start_matches('!').to_string()),
+        Value::Mapping(map) if map.len() == 1 => map.keys().next()?.as_str().map(str::to_string),
+        _ => None,
+    }
+}
+
+/// enum の値の中身
+fn body_mut(value: &mut Value) -> Option<&mut Value> {
+    match value {
+        Value::Tagged(tagged) => Some(&mut tagged.value),
+        Value::Mapping(map) if map.len() == 1 => map.values_mut().next(),
+        _ => None,
+    }
+}
+
+/// 0 → 1: `{cmd, args}` を `!Shell {cmd, args}` にする
+fn tag_shell_commands(value: &mut Value, format: ThemeFormat) {
+    for_each_content(value, |content| {
+        if content
+            .as_mapping()
+            .is_some_and(|map| map.contains_key("cmd"))
+        {
+            *content = tagged(format, "Shell", content.clone());
+        }
+    });
+}
+
+/// 1 → 2: `{build_in: !Os {...}}` を `!BuildIn {command: !Os {...}}` にする。
+/// 当時の `Cmd` は環境変数名をそのまま書いていたため `$` を付け、
+/// `Git` の色は `"#RRGGBB"` で書いていたため `!Rgb [r, g, b]` にする
+fn tag_build_in_commands(value: &mut Value, format: ThemeFormat) {
+    for_each_content(value, |content| {
+        let Some(mut command) = content
+            .as_mapping_mut()
+            .filter(|map| map.len() == 1)
+            .and_then(|map| map.remove("build_in"))
+        else {
+            return;
+        };
+        let tag = tag_of(&command);
+        match (tag.as_deref(), body_mut(&mut command)) {
+            (Some("Cmd"), Some(Value::Mapping(body))) => {
+                for key in ["last_status", "last_command_executed"] {
+                    if let Some(Value::String(var)) = body.get_mut(key)
+                        && !var.starts_with('$')
+                    {
+                        var.insert(0, '$');
+                    }
+                }
+            }
+            (Some("Git"), Some(Value::Mapping(body))) => {
+                if let Some(Value::Mapping(options)) = body.get_mut("options") {
+                    for (_, color) in options.iter_mut() {
+                        if let Some((r, g, b)) = color.as_str().and_then(parse_hex) {
+                            let rgb = Value::Sequence(vec![r.into(), g.into(), b.into()]);
+                            *color = tagged(format, "Rgb", rgb);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        let mut body = Mapping::new();
+        body.insert("command".into(), command);
+        *content = tagged(format, "BuildIn", Value::Mapping(body));
+    });
+}
+
+fn parse_hex(s: &str) -> Option<(u8, u8, u8)> {
+    let hex = s
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.is_ascii())?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
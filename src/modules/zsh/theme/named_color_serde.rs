@@ -1,15 +1,22 @@
 use serde::{self, Deserialize, Deserializer, Serializer};
 use zsh_seq::NamedColor;
 
+use super::color_parse;
+
 // Helper function to serialize NamedColor to a string
 pub fn serialize<S>(color: &NamedColor, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
+    serializer.serialize_str(&to_string(color))
+}
+
+// Helper function to format NamedColor as the string used in theme files
+pub fn to_string(color: &NamedColor) -> String {
     // For serialization, we convert NamedColor to its Zsh string representation.
     // However, for YAML, we want a more human-readable/direct representation.
     // Let's define a specific string format for each variant.
-    let s = match color {
+    match color {
         NamedColor::Black => "Black".to_string(),
         NamedColor::Red => "Red".to_string(),
         NamedColor::Green => "Green".to_string(),
@@ -28,8 +35,7 @@ where
         NamedColor::LightWhite => "LightWhite".to_string(),
         NamedColor::Code256(code) => format!("Code256({})", code),
         NamedColor::FullColor((r, g, b)) => format!("FullColor({},{},{})", r, g, b),
-    };
-    serializer.serialize_str(&s)
+    }
 }
 
 // Helper function to deserialize NamedColor from a string
@@ -37,75 +43,53 @@ pub fn deserialize<'de, D>(deserializer: D) -> Result<NamedColor, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s = String::deserialize(deserializer)?;
+    let s = ColorInput::deserialize(deserializer)?.into_string();
     deserialize_from_str(&s).map_err(serde::de::Error::custom)
 }
 
-pub fn deserialize_from_str(s: &str) -> Result<NamedColor, String> {
-    if s == "Black" {
-        Ok(NamedColor::Black)
-    } else if s == "Red" {
-        Ok(NamedColor::Red)
-    } else if s == "Green" {
-        Ok(NamedColor::Green)
-    } else if s == "Yellow" {
-        Ok(NamedColor::Yellow)
-    } else if s == "Blue" {
-        Ok(NamedColor::Blue)
-    } else if s == "Magenta" {
-        Ok(NamedColor::Magenta)
-    } else if s == "Cyan" {
-        Ok(NamedColor::Cyan)
-    } else if s == "White" {
-        Ok(NamedColor::White)
-    } else if s == "LightBlack" {
-        Ok(NamedColor::LightBlack)
-    } else if s == "LightRed" {
-        Ok(NamedColor::LightRed)
-    } else if s == "LightGreen" {
-        Ok(NamedColor::LightGreen)
-    } else if s == "LightYellow" {
-        Ok(NamedColor::LightYellow)
-    } else if s == "LightBlue" {
-        Ok(NamedColor::LightBlue)
-    } else if s == "LightMagenta" {
-        Ok(NamedColor::LightMagenta)
-    } else if s == "LightCyan" {
-        Ok(NamedColor::LightCyan)
-    } else if s == "LightWhite" {
-        Ok(NamedColor::LightWhite)
-    } else if s.starts_with("Code256(") && s.ends_with(')') {
-        let code_str = &s[8..s.len() - 1];
-        let code = code_str
-            .parse::<u8>()
-            .map_err(|e| format!("Invalid Code256 format: {}", e))?;
-        Ok(NamedColor::Code256(code))
-    } else if s.starts_with("FullColor(") && s.ends_with(')') {
-        let parts: Vec<&str> = s[10..s.len() - 1].split(',').collect();
-        if parts.len() == 3 {
-            let r = parts[0]
-                .trim()
-                .parse::<u8>()
-                .map_err(|e| format!("Invalid FullColor format (R): {}", e))?;
-            let g = parts[1]
-                .trim()
-                .parse::<u8>()
-                .map_err(|e| format!("Invalid FullColor format (G): {}", e))?;
-            let b = parts[2]
-                .trim()
-                .parse::<u8>()
-                .map_err(|e| format!("Invalid FullColor format (B): {}", e))?;
-            Ok(NamedColor::FullColor((r, g, b)))
-        } else {
-            Err(format!(
-                "Invalid FullColor format: {}. Expected FullColor(r,g,b)",
-                s
-            ))
+// Colours may also be written as a bare 0-255 number, such as `bg: 208` in YAML
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(super) enum ColorInput {
+    Code(u8),
+    Text(String),
+}
+
+impl ColorInput {
+    pub(super) fn into_string(self) -> String {
+        match self {
+            Self::Code(code) => code.to_string(),
+            Self::Text(s) => s,
         }
-    } else {
-        Err(format!(
-            "Unknown NamedColor variant or invalid format: {}",
-            s
-        ))
+    }
+}
+
+pub fn deserialize_from_str(s: &str) -> Result<NamedColor, String> {
+    color_parse::parse(s)
+}
+
+/// `#[serde(with = "named_color_serde::list")]` で色の列を読み書きする
+pub mod list {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use zsh_seq::NamedColor;
+
+    use super::ColorInput;
+
+    pub fn serialize<S>(colors: &[NamedColor], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(colors.iter().map(super::to_string))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<NamedColor>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<ColorInput>::deserialize(deserializer)?
+            .into_iter()
+            .map(|color| super::deserialize_from_str(&color.into_string()))
+            .collect::<Result<_, _>>()
+            .map_err(serde::de::Error::custom)
     }
 }
@@ -0,0 +1,281 @@
+use serde_yaml::Value;
+use std::{collections::BTreeMap, fmt};
+use zsh_seq::NamedColor;
+
+use super::{
+    color_named_color::{color_string, prompts_color},
+    color_parse,
+    gradient::stop_color_string,
+    manager::ThemeFormat,
+    named_color_serde,
+};
+
+pub const PALETTE_KEY: &str = "palette";
+
+/// `$name` で参照できる色の置き場所
+#[derive(Clone, Copy)]
+enum Slot {
+    /// `named_color_serde` の色 (`fg`, `bg`, `pc`, `sc`)
+    Color,
+    /// `accent` の色またはグラデーションの `"$name:pos"`
+    Accent,
+    /// 組み込みコマンドの `color` 文字列
+    BuildIn,
+    /// Git の `*_color_option`
+    Git,
+}
+
+fn slot_of(key: &str) -> Option<Slot> {
+    match key {
+        "fg" | "bg" | "pc" | "sc" => Some(Slot::Color),
+        "accent" => Some(Slot::Accent),
+        "color" => Some(Slot::BuildIn),
+        key if key.ends_with("_color_option") => Some(Slot::Git),
+        _ => None,
+    }
+}
+
+/// 読み込んだときに `$name` で書かれていた色の欄。
+/// パスごとに、書かれていた参照と解決した値を持つ
+#[derive(Clone, Debug, Default)]
+pub struct References(BTreeMap<String, (String, Value)>);
+
+/// 解決できなかった `$name` の参照
+#[derive(Debug)]
+pub struct Unresolved {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Unresolved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// パレットの色を読む。書き方は他の色の欄と同じ
+pub fn parse_entry(s: &str) -> Option<NamedColor> {
+    color_parse::parse(s).ok()
+}
+
+/// `value` の `palette` を読み、色の欄の `$name` をパレットの色に置き換える。
+/// 組み込みコマンドと Git の色は、どの書き方でも zsh_prompts が読める形に直す。
+/// 置き換えた欄は `unresolve` で参照に戻せるよう返す
+pub fn resolve(value: &mut Value, format: ThemeFormat) -> Result<References, String> {
+    let (references, errors) = resolve_recording(value, format);
+    if errors.is_empty() {
+        Ok(references)
+    } else {
+        Err(errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; "))
+    }
+}
+
+/// `resolve` と同じだが、最初の誤りで止めずに全ての誤りを返す。
+/// 解決できなかった値はそのまま残す
+pub fn resolve_all(value: &mut Value, format: ThemeFormat) -> Vec<Unresolved> {
+    resolve_recording(value, format).1
+}
+
+fn resolve_recording(value: &mut Value, format: ThemeFormat) -> (References, Vec<Unresolved>) {
+    let mut references = BTreeMap::new();
+    let mut errors = Vec::new();
+    let mut palette = BTreeMap::new();
+    if let Some(Value::Mapping(map)) = value.get(PALETTE_KEY) {
+        for (name, entry) in map {
+            let Some(name) = name.as_str() else {
+                continue;
+            };
+            match entry.as_str().and_then(parse_entry) {
+                Some(color) => {
+                    palette.insert(name.to_string(), color);
+                }
+                None => errors.push(Unresolved {
+                    path: format!("{}.{}", PALETTE_KEY, name),
+                    message: format!("invalid palette colour {:?}", entry),
+                }),
+            }
+        }
+    }
+
+    visit(value, None, "", "", &mut |value, slot, path, id| {
+        let Some(s) = value.as_str() else {
+            return;
+        };
+        let (reference, pos) = match (slot, s.split_once(':')) {
+            (Slot::Accent, Some((reference, pos))) => (reference, Some(pos)),
+            _ => (s, None),
+        };
+        let color = match reference.strip_prefix('$') {
+            Some(name) => match palette.get(name) {
+                Some(color) => *color,
+                None => {
+                    errors.push(Unresolved {
+                        path: path.to_string(),
+                        message: format!("unknown palette colour `${}`", name),
+                    });
+                    return;
+                }
+            },
+            // 組み込みコマンドと Git の色は zsh_prompts が読める書き方に揃える
+            None if matches!(slot, Slot::BuildIn | Slot::Git) => match color_parse::parse(s) {
+                Ok(color) => color,
+                Err(message) => {
+                    errors.push(Unresolved {
+                        path: path.to_string(),
+                        message,
+                    });
+                    return;
+                }
+            },
+            None => return,
+        };
+        let text = reference.starts_with('$').then(|| s.to_string());
+        *value = match (slot, pos) {
+            (Slot::Accent, Some(pos)) => {
+                Value::String(format!("{}:{}", stop_color_string(&color), pos))
+            }
+            (Slot::Color | Slot::Accent, _) => Value::String(named_color_serde::to_string(&color)),
+            (Slot::BuildIn, _) => Value::String(color_string(color)),
+            (Slot::Git, _) => match format.to_value(&prompts_color(color)) {
+                Ok(git) => git,
+                Err(e) => {
+                    errors.push(Unresolved {
+                        path: path.to_string(),
+                        message: e,
+                    });
+                    return;
+                }
+            },
+        };
+        if let Some(text) = text {
+            references.insert(id.to_string(), (text, value.clone()));
+        }
+    });
+    (References(references), errors)
+}
+
+/// `resolve` の逆。読み込んだときに `$name` で書かれていた欄のうち、
+/// 色が変わっていないものだけを参照に戻す。初めから色で書かれていた欄はそのまま残す
+pub fn unresolve(value: &mut Value, references: &References) {
+    if references.0.is_empty() {
+        return;
+    }
+    visit(value, None, "", "", &mut |value, slot, _, id| {
+        let Some((text, resolved)) = references.0.get(id) else {
+            return;
+        };
+        let restored = match (slot, value.as_str(), resolved.as_str()) {
+            // グラデーションのストップは位置が変わっていても色が同じなら参照に戻す
+            (Slot::Accent, Some(current), Some(resolved)) if current.contains(':') => {
+                // 色の書き方に `:` は含まれないので、最初の `:` より後ろが位置と進み方
+                let (color, pos) = current.split_once(':').unwrap_or((current, ""));
+                let resolved = resolved.split_once(':').map_or(resolved, |(c, _)| c);
+                let name = text.split(':').next().unwrap_or(text);
+                same_color(color, resolved).then(|| format!("{}:{}", name, pos))
+            }
+            (_, Some(current), Some(resolved)) => {
+                same_color(current, resolved).then(|| text.clone())
+            }
+            _ => (untagged(value) == untagged(resolved)).then(|| text.clone()),
+        };
+        if let Some(restored) = restored {
+            *value = Value::String(restored);
+        }
+    });
+}
+
+/// 2 つの色の書き方が同じ色を表すか
+fn same_color(a: &str, b: &str) -> bool {
+    match (parse_entry(a), parse_entry(b)) {
+        (Some(a), Some(b)) => named_color_serde::to_string(&a) == named_color_serde::to_string(&b),
+        _ => a == b,
+    }
+}
+
+/// YAML のタグ `!Tag value` を、TOML や JSON と同じ `{Tag: value}` の形にする
+fn untagged(value: &Value) -> Value {
+    match value {
+        Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string();
+            let mut map = serde_yaml::Mapping::new();
+            map.insert(
+                Value::String(tag.trim_start_matches('!').to_string()),
+                untagged(&tagged.value),
+            );
+            Value::Mapping(map)
+        }
+        Value::Mapping(map) => Value::Mapping(
+            map.iter()
+                .map(|(key, value)| (key.clone(), untagged(value)))
+                .collect(),
+        ),
+        Value::Sequence(items) => Value::Sequence(items.iter().map(untagged).collect()),
+        value => value.clone(),
+    }
+}
+
+/// 色の欄を辿り、見つかった値ごとに `f` を呼ぶ。
+/// `path` は誤りの場所を示す道のり、`id` は書式によらない欄の識別子で、
+/// YAML のタグと TOML や JSON の `{Tag: ...}` のように要素が 1 つの対応表の鍵を含まない
+fn visit(
+    value: &mut Value,
+    slot: Option<Slot>,
+    path: &str,
+    id: &str,
+    f: &mut impl FnMut(&mut Value, Slot, &str, &str),
+) {
+    // Git の色は `!Rgb [r, g, b]` のような値全体を置き換える
+    if let Some(Slot::Git) = slot {
+        f(value, Slot::Git, path, id);
+        return;
+    }
+    match value {
+        Value::Mapping(map) => {
+            let single = map.len() == 1;
+            for (key, child) in map.iter_mut() {
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    // `!perSegment` の区切りの番号
+                    Value::Number(index) => index.to_string(),
+                    _ => continue,
+                };
+                let key = key.as_str();
+                let child_slot = match key {
+                    // 環境変数や引数の中は色ではない
+                    "envs" | "args" | PALETTE_KEY => continue,
+                    // `{gradient: [...]}` 形式の accent はタグ名や `color` などの下も accent として扱う
+                    key => slot
+                        .filter(|slot| matches!(slot, Slot::Accent))
+                        .or_else(|| slot_of(key)),
+                };
+                let child_path = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let child_id = if single {
+                    id.to_string()
+                } else {
+                    format!("{}.{}", id, key)
+                };
+                visit(child, child_slot, &child_path, &child_id, f);
+            }
+        }
+        Value::Sequence(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                let child_path = format!("{}.{}", path, i);
+                visit(item, slot, &child_path, &format!("{}.{}", id, i), f);
+            }
+        }
+        Value::Tagged(tagged) => visit(&mut tagged.value, slot, path, id, f),
+        _ => {
+            if let Some(slot) = slot {
+                f(value, slot, path, id);
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{collections::BTreeMap, fmt};
+use zsh_seq::NamedColor;
+
+use super::named_color_serde::{self, ColorInput};
+
+/// 区切りの番号ごとに色を決めたアクセント。
+/// 番号が書かれていない区切りは、それより前で最も近い番号の色を使う
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PerSegment {
+    pub colors: BTreeMap<usize, NamedColor>,
+}
+
+impl PerSegment {
+    /// `index` 番目の区切りの色。色が 1 つも無ければ White
+    pub fn get(&self, index: usize) -> NamedColor {
+        self.colors
+            .range(..=index)
+            .next_back()
+            .or_else(|| self.colors.first_key_value())
+            .map_or(NamedColor::White, |(_, color)| *color)
+    }
+
+    /// 番号の代わりに位置 `progress` (0.0〜1.0) で取る。0.0 が最初の番号、1.0 が最後の番号になる
+    pub fn get_progress(&self, progress: f32) -> NamedColor {
+        let last = self.colors.last_key_value().map_or(0, |(index, _)| *index);
+        self.get((progress.clamp(0.0, 1.0) * last as f32).round() as usize)
+    }
+}
+
+/// TOML の表のキーは文字列しか使えないので、番号は文字列として書く
+impl Serialize for PerSegment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(
+            self.colors
+                .iter()
+                .map(|(index, color)| (index.to_string(), named_color_serde::to_string(color))),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for PerSegment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // YAML では `0: Red` と番号のまま書ける
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum IndexInput {
+            Number(u64),
+            Text(String),
+        }
+
+        struct PerSegmentVisitor;
+        impl<'de> de::Visitor<'de> for PerSegmentVisitor {
+            type Value = PerSegment;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map from separator numbers to colours")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                let mut colors = BTreeMap::new();
+                while let Some(index) = map.next_key::<IndexInput>()? {
+                    let index = match index {
+                        IndexInput::Number(index) => usize::try_from(index).ok(),
+                        IndexInput::Text(ref text) => text.trim().parse().ok(),
+                    }
+                    .ok_or_else(|| {
+                        let index = match index {
+                            IndexInput::Number(index) => index.to_string(),
+                            IndexInput::Text(text) => text,
+                        };
+                        de::Error::custom(format!(
+                            "invalid separator number `{}` (expected 0, 1, 2, ...)",
+                            index
+                        ))
+                    })?;
+                    let color = named_color_serde::deserialize_from_str(
+                        &map.next_value::<ColorInput>()?.into_string(),
+                    )
+                    .map_err(de::Error::custom)?;
+                    colors.insert(index, color);
+                }
+                Ok(PerSegment { colors })
+            }
+        }
+        deserializer.deserialize_map(PerSegmentVisitor)
+    }
+}
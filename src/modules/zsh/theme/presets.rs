@@ -0,0 +1,137 @@
+use std::{io, path::PathBuf};
+
+use super::{
+    manager::{self, ThemeFormat},
+    preview,
+    prompt_theme::PromptTheme,
+};
+
+/// コードで組み立てるプリセットの名前
+const BUILT_IN: [&str; 2] = ["default", "infinite"];
+
+/// `extends` で参照できるプリセット名。組み込みのものに続けて `GALLERY` の順に並べる
+pub const NAMES: [&str; BUILT_IN.len() + GALLERY.len()] = {
+    let mut names = [""; BUILT_IN.len() + GALLERY.len()];
+    let mut i = 0;
+    while i < BUILT_IN.len() {
+        names[i] = BUILT_IN[i];
+        i += 1;
+    }
+    let mut j = 0;
+    while j < GALLERY.len() {
+        names[i + j] = GALLERY[j].0;
+        j += 1;
+    }
+    names
+};
+
+/// `include_str!` で埋め込んだプリセット。名前、説明、YAML
+const GALLERY: [(&str, &str, &str); 6] = [
+    (
+        "minimal",
+        "One line with only the directory and Git",
+        include_str!("../../../assets/presets/minimal.yaml"),
+    ),
+    (
+        "powerline",
+        "Classic Powerline segments filled with background colours",
+        include_str!("../../../assets/presets/powerline.yaml"),
+    ),
+    (
+        "lean",
+        "No separators, details on the first line and a clean input line",
+        include_str!("../../../assets/presets/lean.yaml"),
+    ),
+    (
+        "boxed",
+        "Two framed lines with rounded separators",
+        include_str!("../../../assets/presets/boxed.yaml"),
+    ),
+    (
+        "high-contrast",
+        "Bright 16-colour text on black",
+        include_str!("../../../assets/presets/high-contrast.yaml"),
+    ),
+    (
+        "pastel",
+        "Soft gradient accents and rounded separators",
+        include_str!("../../../assets/presets/pastel.yaml"),
+    ),
+];
+
+/// 埋め込んだプリセットを読み込む。テーマファイルと同じく変換やパレットの解決を行う
+pub fn gallery(name: &str) -> Option<PromptTheme> {
+    let (_, _, yaml) = GALLERY.iter().find(|(n, _, _)| *n == name)?;
+    let path = PathBuf::from(format!("{}.{}", name, ThemeFormat::Yaml.extension()));
+    match manager::parse_theme(yaml, &path) {
+        Ok(theme) => Some(theme),
+        Err(e) => {
+            eprintln!("Failed to load preset `{}`: {}", name, e);
+            None
+        }
+    }
+}
+
+fn description(name: &str) -> &'static str {
+    match name {
+        "default" => "The default theme",
+        "infinite" => "Rainbow accents with OS, directory, Git and time",
+        _ => GALLERY
+            .iter()
+            .find(|(n, _, _)| *n == name)
+            .map(|(_, description, _)| *description)
+            .unwrap_or(""),
+    }
+}
+
+/// プリセットを見本付きで一覧表示する
+pub fn list() {
+    for name in PromptTheme::PRESETS {
+        println!("{} - {}", name, description(name));
+        if let Some(theme) = PromptTheme::preset(name) {
+            println!("{}\n", preview::render(&theme));
+        }
+    }
+}
+
+/// プリセットを使用中のテーマとして保存する
+pub fn apply(name: &str) -> io::Result<()> {
+    let theme = PromptTheme::preset(name).ok_or_else(|| {
+        io::Error::other(format!(
+            "Unknown preset `{}`. Run `zsh-infinite theme list-presets` to see the presets.",
+            name
+        ))
+    })?;
+    manager::save_theme(&theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_yaml::{Mapping, Value};
+
+    use super::*;
+    use crate::zsh::theme::extends;
+
+    #[test]
+    fn every_preset_resolves_through_extends() {
+        for name in PromptTheme::PRESETS {
+            let mut map = Mapping::new();
+            map.insert("extends".into(), name.into());
+            let value = extends::resolve(
+                Value::Mapping(map),
+                ThemeFormat::Yaml,
+                Path::new("test.yaml"),
+            )
+            .unwrap_or_else(|e| panic!("preset `{}` did not resolve: {}", name, e));
+            let theme: Result<PromptTheme, _> = ThemeFormat::Yaml.from_value(value);
+            assert!(
+                theme.is_ok(),
+                "preset `{}` did not load: {:?}",
+                name,
+                theme.err()
+            );
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use crossterm::terminal;
+use unicode_width::UnicodeWidthStr;
+use zsh_prompts::Commands;
+use zsh_seq::{NamedColor, ZshPromptBuilder, ZshSequence};
+
+use super::{
+    appearance,
+    color_depth::ColorDepth,
+    color_named_color::ToNamedColor,
+    color_parse,
+    compile::wrap,
+    gradient::Gradient,
+    prompt_theme::{PromptContent, PromptTheme},
+    rainbow::DynamicHues,
+};
+use crate::zsh::prompt::{Prompt, PromptConnection, PromptCurveLine, apply_accent_scope};
+
+/// 見本の幅の上限
+const MAX_WIDTH: usize = 80;
+
+/// テーマの見本を、端末にそのまま表示できる文字列にする。
+/// セグメントはコマンドを実行せず、見本の文字列で表示する
+pub fn render(theme: &PromptTheme) -> String {
+    let width = terminal::size()
+        .map(|(w, _)| w as usize)
+        .unwrap_or(MAX_WIDTH)
+        .min(MAX_WIDTH);
+    let mut theme = theme.clone();
+    if let Some(appearance) = appearance::current() {
+        theme.apply_appearance(appearance);
+    }
+    let hues = DynamicHues::now();
+    let mut prompts: Vec<Prompt> = theme
+        .prompt_contents_list
+        .iter()
+        .map(|contents| {
+            let mut prompt = Prompt::new(hues);
+            prompt.extend_left(contents.left.iter().map(sample).collect());
+            prompt.extend_right(contents.right.iter().map(sample).collect());
+            prompt
+        })
+        .collect();
+    let tail = apply_accent_scope(&theme, &mut prompts);
+    let mut builder = ZshPromptBuilder::new();
+    for (i, (contents, prompt)) in theme.prompt_contents_list.iter().zip(&prompts).enumerate() {
+        let left = prompt.render_left(contents);
+        let right = prompt.render_right(contents);
+
+        let curved_lines = PromptCurveLine::from(contents.connection);
+        let h = &curved_lines.horizontal;
+        let connection = contents.connection.to_string();
+        let conn_width = UnicodeWidthStr::width(connection.as_str()).max(1);
+        let side_width = UnicodeWidthStr::width(curved_lines.top_left.as_str()) + conn_width;
+        let used = left.len() + right.len() + side_width * 2;
+        let fill = connection.repeat(width.saturating_sub(used) / conn_width);
+        let (start, end) = if i == 0 {
+            (&curved_lines.top_left, &curved_lines.top_right)
+        } else {
+            (&curved_lines.cross_left, &curved_lines.cross_right)
+        };
+        let row = ZshPromptBuilder::new()
+            .color(contents.color.sc)
+            .str(start)
+            .str(h)
+            .end_color()
+            .connect(left)
+            .color(contents.color.pc)
+            .str(&fill)
+            .end_color()
+            .connect(right)
+            .color(contents.color.sc)
+            .str(h)
+            .str(end)
+            .end_color();
+        builder = builder.connect(row).newline();
+    }
+
+    let (sc, connection) = match theme.prompt_contents_list.last() {
+        Some(contents) => (contents.color.sc, contents.connection),
+        None => (NamedColor::LightBlack, PromptConnection::default()),
+    };
+    let curved_lines = PromptCurveLine::from(connection);
+    let h = &curved_lines.horizontal;
+    let start = format!("{}{} ", curved_lines.bottom_left, h);
+    let end = format!("{}{}", h, curved_lines.bottom_right);
+    let gap = width.saturating_sub(
+        UnicodeWidthStr::width(start.as_str()) + UnicodeWidthStr::width(end.as_str()),
+    );
+    let sc = theme.accent_tail(tail, &hues).unwrap_or(sc);
+    let last = ZshPromptBuilder::new()
+        .color(sc)
+        .str(&start)
+        .end_color()
+        .str(&" ".repeat(gap))
+        .color(sc)
+        .str(&end)
+        .end_color();
+    to_ansi(&ColorDepth::detect().quantize_prompt(&builder.connect(last).build()))
+}
+
+/// セグメントの見本
+fn sample(content: &PromptContent) -> Vec<ZshSequence> {
+    let color = |color: &Option<String>| color.as_deref().and_then(|c| color_parse::parse(c).ok());
+    match content {
+        PromptContent::Literal { value, fg, bg } => wrap(*fg, *bg, value.clone()),
+        // 取り込んだテーマのコマンドは確認前に実行しない
+        PromptContent::Shell { cmd, fg, bg, .. } => wrap(*fg, *bg, format!("$({})", cmd)),
+        PromptContent::BuildIn { command } => match command {
+            Commands::Pwd { color: c } => wrap(color(c), None, "~/projects".to_string()),
+            Commands::Cmd { color: c, .. } => wrap(color(c), None, "✔".to_string()),
+            Commands::Os { color: c } => wrap(color(c), None, "\u{f17c}".to_string()),
+            Commands::Time { color: c } => wrap(color(c), None, "12:34:56".to_string()),
+            Commands::Git { options, .. } => {
+                let color = |c: Option<zsh_prompts::Color>| {
+                    c.or(options.default_color_option)
+                        .map(|c| c.to_named_color())
+                };
+                let mut seqs = wrap(
+                    color(options.git_icon_color_option),
+                    None,
+                    "\u{e0a0}".to_string(),
+                );
+                seqs.push(ZshSequence::Literal(" ".to_string()));
+                seqs.extend(wrap(
+                    color(options.branch_color_option),
+                    None,
+                    "main".to_string(),
+                ));
+                seqs
+            }
+        },
+    }
+}
+
+/// グラデーションを `width` 文字の色の帯にし、その下に各ストップの位置を番号で示す
+pub fn gradient_bar(gradient: &Gradient, width: usize) -> String {
+    let depth = ColorDepth::detect();
+    let width = width.max(2);
+    let mut bar = String::new();
+    for i in 0..width {
+        let color = gradient.get(i as f32 / (width - 1) as f32);
+        bar.push_str(&ansi(depth.quantize(color), true));
+        bar.push(' ');
+    }
+    bar.push_str("\x1b[0m");
+
+    let mut markers = vec![' '; width];
+    for (i, stop) in gradient.stops.iter().enumerate() {
+        let column = (stop.pos.clamp(0.0, 1.0) * (width - 1) as f32).round() as usize;
+        // 10 個目以降のストップは番号の代わりに `^` で示す
+        markers[column] = char::from_digit(i as u32 + 1, 10).unwrap_or('^');
+    }
+    format!(
+        "{}\n{}",
+        bar,
+        markers.into_iter().collect::<String>().trim_end()
+    )
+}
+
+/// 色を端末のエスケープシーケンスにする
+pub fn ansi(color: NamedColor, background: bool) -> String {
+    let base = if background { 40 } else { 30 };
+    let code = match color {
+        NamedColor::Black => base.to_string(),
+        NamedColor::Red => (base + 1).to_string(),
+        NamedColor::Green => (base + 2).to_string(),
+        NamedColor::Yellow => (base + 3).to_string(),
+        NamedColor::Blue => (base + 4).to_string(),
+        NamedColor::Magenta => (base + 5).to_string(),
+        NamedColor::Cyan => (base + 6).to_string(),
+        NamedColor::White => (base + 7).to_string(),
+        NamedColor::LightBlack => (base + 60).to_string(),
+        NamedColor::LightRed => (base + 61).to_string(),
+        NamedColor::LightGreen => (base + 62).to_string(),
+        NamedColor::LightYellow => (base + 63).to_string(),
+        NamedColor::LightBlue => (base + 64).to_string(),
+        NamedColor::LightMagenta => (base + 65).to_string(),
+        NamedColor::LightCyan => (base + 66).to_string(),
+        NamedColor::LightWhite => (base + 67).to_string(),
+        NamedColor::Code256(n) => format!("{};5;{}", base + 8, n),
+        NamedColor::FullColor((r, g, b)) => format!("{};2;{};{};{}", base + 8, r, g, b),
+    };
+    format!("\x1b[{}m", code)
+}
+
+/// zsh のプロンプト文字列のうち、色と太字のエスケープを端末のエスケープシーケンスにする
+pub fn to_ansi(prompt: &str) -> String {
+    let mut out = String::new();
+    let mut chars = prompt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => out.push('%'),
+            Some(kind @ ('F' | 'K')) => {
+                let mut arg = String::new();
+                if chars.next_if_eq(&'{').is_some() {
+                    arg = chars.by_ref().take_while(|&c| c != '}').collect();
+                }
+                if let Ok(color) = color_parse::parse(&arg) {
+                    out.push_str(&ansi(color, kind == 'K'));
+                }
+            }
+            Some('f') => out.push_str("\x1b[39m"),
+            Some('k') => out.push_str("\x1b[49m"),
+            Some('B') => out.push_str("\x1b[1m"),
+            Some('b') => out.push_str("\x1b[22m"),
+            // `%{...%}` の中はそのまま出力する
+            Some('{') => {
+                while let Some(c) = chars.next() {
+                    if c == '%' && chars.next_if_eq(&'}').is_some() {
+                        break;
+                    }
+                    out.push(c);
+                }
+            }
+            _ => {}
+        }
+    }
+    out.push_str("\x1b[0m");
+    out
+}
@@ -0,0 +1,210 @@
+use dialoguer::Confirm;
+use dialoguer::theme::ColorfulTheme;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::zsh::theme::{
+    manager::{self, ThemeFormat},
+    prompt_theme::PromptTheme,
+};
+
+/// プロファイルを置くディレクトリ名 (設定ディレクトリからの相対)
+const THEMES_DIR: &str = "themes";
+/// 選択中のプロファイル名を記録するファイル。テーマファイルとは別に保存する
+const ACTIVE_FILE: &str = "active_theme";
+
+pub fn themes_dir() -> Option<PathBuf> {
+    manager::get_config_dir().map(|dir| dir.join(THEMES_DIR))
+}
+
+fn active_file() -> Option<PathBuf> {
+    manager::get_config_dir().map(|dir| dir.join(ACTIVE_FILE))
+}
+
+/// 選択中のプロファイル名。未選択なら `None`
+pub fn active_profile() -> Option<String> {
+    let content = fs::read_to_string(active_file()?).ok()?;
+    let name = content.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// プロファイル名に対応するテーマファイル。存在しなければ `None`
+pub fn profile_path(name: &str) -> Option<PathBuf> {
+    manager::find_theme_file(&themes_dir()?, name)
+}
+
+/// 保存されているプロファイル名の一覧 (名前順)
+pub fn profiles() -> Vec<String> {
+    let Some(dir) = themes_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    ThemeFormat::ALL
+                        .iter()
+                        .any(|format| format.extension() == ext)
+                })
+        })
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn validate_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(io::Error::other(format!(
+            "Invalid profile name {:?}: must be non-empty, not start with '.', and not contain path separators.",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn set_active(name: Option<&str>) -> io::Result<()> {
+    let path = active_file().ok_or_else(|| {
+        io::Error::other("Could not determine project directories for theme profiles.")
+    })?;
+    match name {
+        Some(name) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, format!("{}\n", name))
+        }
+        None if path.exists() => fs::remove_file(path),
+        None => Ok(()),
+    }
+}
+
+/// プロファイルの一覧を表示する。選択中のものには `*` を付ける
+pub fn list() {
+    let active = active_profile();
+    let names = profiles();
+    if names.is_empty() {
+        println!("No saved theme profiles.");
+    }
+    for name in &names {
+        let mark = if active.as_deref() == Some(name.as_str()) {
+            "*"
+        } else {
+            " "
+        };
+        println!("{} {}", mark, name);
+    }
+    match active {
+        None => println!("(using the default theme file)"),
+        Some(name) if !names.contains(&name) => {
+            println!(
+                "(active profile {:?} is missing; using the default theme file)",
+                name
+            )
+        }
+        Some(_) => {}
+    }
+}
+
+/// プロファイルを切り替える
+pub fn use_profile(name: &str) -> io::Result<()> {
+    validate_name(name)?;
+    if profile_path(name).is_none() {
+        return Err(io::Error::other(format!(
+            "Theme profile {:?} not found. Run `zsh-infinite theme list` to see saved profiles.",
+            name
+        )));
+    }
+    set_active(Some(name))?;
+    eprintln!("Switched to theme profile {:?}.", name);
+    Ok(())
+}
+
+/// 現在のテーマを名前を付けて保存し、そのプロファイルに切り替える
+pub fn save_as(name: &str, format: Option<ThemeFormat>) -> io::Result<()> {
+    validate_name(name)?;
+    let dir = themes_dir().ok_or_else(|| {
+        io::Error::other("Could not determine project directories for theme profiles.")
+    })?;
+    let source = manager::get_theme_file_path();
+    let mut theme = manager::load_theme_from(None)?;
+    if let Some(source) = &source {
+        rebase_extends(&mut theme, source);
+    }
+
+    let format = format
+        .or_else(|| profile_path(name).map(|path| ThemeFormat::from_path(&path)))
+        .or_else(|| source.as_deref().map(ThemeFormat::from_path))
+        .unwrap_or(ThemeFormat::Yaml);
+    let path = dir.join(format!("{}.{}", name, format.extension()));
+    // 別の形式で保存されていた同名のプロファイルは取り除く
+    for other in ThemeFormat::ALL {
+        let other_path = dir.join(format!("{}.{}", name, other.extension()));
+        if other_path != path && other_path.exists() {
+            fs::remove_file(other_path)?;
+        }
+    }
+    manager::save_theme_to(&theme, &path)?;
+    set_active(Some(name))?;
+    eprintln!("Switched to theme profile {:?}.", name);
+    Ok(())
+}
+
+/// 継承元が相対パスの場合、保存先が変わっても同じファイルを指すよう絶対パスにする
+fn rebase_extends(theme: &mut PromptTheme, source: &Path) {
+    let Some(extends) = theme.extends.as_deref() else {
+        return;
+    };
+    if PromptTheme::preset(extends).is_some() {
+        return;
+    }
+    let expanded = shellexpand::full(extends)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| extends.to_string());
+    if Path::new(&expanded).is_absolute() {
+        return;
+    }
+    if let Some(dir) = source.parent() {
+        let path = dir.join(expanded);
+        let path = path.canonicalize().unwrap_or(path);
+        theme.extends = Some(path.to_string_lossy().into_owned());
+    }
+}
+
+/// プロファイルを削除する。選択中のものを削除した場合は既定のテーマファイルに戻る
+pub fn delete(name: &str, yes: bool) -> io::Result<()> {
+    validate_name(name)?;
+    let Some(path) = profile_path(name) else {
+        return Err(io::Error::other(format!(
+            "Theme profile {:?} not found.",
+            name
+        )));
+    };
+    if !yes {
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Delete theme profile {:?}?", name))
+            .default(false)
+            .interact()
+            .map_err(io::Error::other)?;
+        if !confirmed {
+            eprintln!("Aborted.");
+            return Ok(());
+        }
+    }
+    fs::remove_file(&path)?;
+    eprintln!("Theme profile {:?} deleted.", name);
+    if active_profile().as_deref() == Some(name) {
+        set_active(None)?;
+        eprintln!("Switched back to the default theme file.");
+    }
+    Ok(())
+}
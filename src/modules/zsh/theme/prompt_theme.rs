@@ -1,9 +1,15 @@
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
 use tokio::process::Command;
 use zsh_seq::{NamedColor, ZshSequence};
 
-use super::color_scheme::PromptColorScheme;
+use super::{
+    appearance::Appearance, color_scheme::PromptColorScheme, palette::References,
+    rainbow::DynamicHues,
+};
 // 変更
 use crate::zsh::{
     prompt::{PromptConnection, PromptSeparation},
@@ -17,26 +23,136 @@ pub enum AccentWhich {
     BackGround,
 }
 
+/// アクセントのグラデーションを、どの範囲で 0.0 から 1.0 まで進めるか
+#[derive(Clone, Debug, Serialize, Deserialize, Default, Copy, PartialEq)]
+pub enum AccentScope {
+    /// 行ごとに最初から始める
+    #[default]
+    Line,
+    /// 1 行目の左から最後の行の右まで、全体で 1 つのグラデーションにする
+    Prompt,
+    /// `Prompt` と同じだが、偶数行目は右から左へ進め、行の端で色が繋がるようにする
+    Snake,
+}
+
+impl AccentScope {
+    fn is_line(&self) -> bool {
+        *self == Self::Line
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PromptTheme {
+    /// テーマファイルのレイアウトの版。古いファイルは読み込み時に変換する
+    #[serde(default)]
+    pub version: u32,
+    /// 継承元のプリセット名またはテーマファイルのパス。ファイルには差分だけを書く
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// 色の欄から `$name` で参照できる色
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub palette: BTreeMap<String, String>,
+    /// 読み込んだときに `$name` で書かれていた色の欄。保存するときはこの欄だけを参照に戻す
+    #[serde(skip)]
+    pub palette_references: References,
     pub prompt_contents_list: Vec<PromptContents>,
+    /// アクセントを行ごとに進めるか、プロンプト全体で進めるか。
+    /// 全体の場合は 1 行目のアクセントを使い、下端の枠と transient prompt も含める
+    #[serde(default, skip_serializing_if = "AccentScope::is_line")]
+    pub accent_scope: AccentScope,
     #[serde(default)]
     pub transient_color: PromptColorScheme,
+    /// 明るい背景の端末で `transient_color` の代わりに使う配色
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transient_color_light: Option<PromptColorScheme>,
+    /// 暗い背景の端末で `transient_color` の代わりに使う配色
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transient_color_dark: Option<PromptColorScheme>,
 }
 
 impl Default for PromptTheme {
     fn default() -> Self {
         Self {
+            version: super::migrate::CURRENT_VERSION,
+            extends: None,
+            palette: BTreeMap::new(),
+            palette_references: References::default(),
             prompt_contents_list: vec![PromptContents::default()],
+            accent_scope: AccentScope::default(),
             transient_color: PromptColorScheme::transient(),
+            transient_color_light: None,
+            transient_color_dark: None,
         }
     }
 }
 impl PromptTheme {
+    /// `extends` で参照できる組み込みプリセット名
+    pub const PRESETS: [&str; super::presets::NAMES.len()] = super::presets::NAMES;
+
     pub fn infinite() -> Self {
         Self {
+            version: super::migrate::CURRENT_VERSION,
+            extends: None,
+            palette: BTreeMap::new(),
+            palette_references: References::default(),
             prompt_contents_list: vec![PromptContents::infinite()],
+            accent_scope: AccentScope::default(),
             transient_color: PromptColorScheme::transient(),
+            transient_color_light: None,
+            transient_color_dark: None,
+        }
+    }
+    /// プロンプト全体でアクセントを進める場合の、最後の位置の色。
+    /// 下端の枠と transient prompt に使う。`index` はその位置の区切りの番号で、
+    /// `Cycle` と `PerSegment` は最後の区切りの続きの色になる。虹の動的なずれは `hues` から取る
+    pub fn accent_tail(&self, index: usize, hues: &DynamicHues) -> Option<NamedColor> {
+        match self.accent_scope {
+            AccentScope::Line => None,
+            AccentScope::Prompt | AccentScope::Snake => self
+                .prompt_contents_list
+                .first()
+                .map(|contents| contents.color.accent.at(index, 1.0, hues)),
+        }
+    }
+    /// 背景の明暗に合わせた配色が 1 つでもあるか
+    pub fn has_variant(&self, appearance: Appearance) -> bool {
+        let variant = |light: &Option<PromptColorScheme>, dark: &Option<PromptColorScheme>| {
+            match appearance {
+                Appearance::Light => light.is_some(),
+                Appearance::Dark => dark.is_some(),
+            }
+        };
+        variant(&self.transient_color_light, &self.transient_color_dark)
+            || self
+                .prompt_contents_list
+                .iter()
+                .any(|contents| variant(&contents.color_light, &contents.color_dark))
+    }
+
+    /// 背景の明暗に合わせた配色を `color` と `transient_color` に反映する。
+    /// その明暗の配色が無い行はそのままにする
+    pub fn apply_appearance(&mut self, appearance: Appearance) {
+        let pick = |light: &Option<PromptColorScheme>, dark: &Option<PromptColorScheme>| {
+            match appearance {
+                Appearance::Light => light.clone(),
+                Appearance::Dark => dark.clone(),
+            }
+        };
+        if let Some(color) = pick(&self.transient_color_light, &self.transient_color_dark) {
+            self.transient_color = color;
+        }
+        for contents in &mut self.prompt_contents_list {
+            if let Some(color) = pick(&contents.color_light, &contents.color_dark) {
+                contents.color = color;
+            }
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "infinite" => Some(Self::infinite()),
+            name => super::presets::gallery(name),
         }
     }
 }
@@ -70,10 +186,19 @@ pub struct PromptContents {
     pub right: Vec<PromptContent>,
     #[serde(default)]
     pub color: super::color_scheme::PromptColorScheme,
+    /// 明るい背景の端末で `color` の代わりに使う配色
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_light: Option<PromptColorScheme>,
+    /// 暗い背景の端末で `color` の代わりに使う配色
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_dark: Option<PromptColorScheme>,
     pub connection: PromptConnection,
     pub left_segment_separators: PromptSegmentSeparators,
     pub right_segment_separators: PromptSegmentSeparators,
     pub accent_which: AccentWhich,
+    /// セグメントの文字と背景の間に保つ WCAG のコントラスト比 (1〜21)。足りない文字色は自動で直す
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_contrast: Option<f32>,
 }
 
 impl Default for PromptContents {
@@ -112,10 +237,13 @@ impl Default for PromptContents {
                 },
             ],
             color: super::color_scheme::PromptColorScheme::default(),
+            color_light: None,
+            color_dark: None,
             connection: PromptConnection::default(),
             left_segment_separators: PromptSegmentSeparators::default(),
             right_segment_separators: PromptSegmentSeparators::default(),
             accent_which: AccentWhich::default(),
+            min_contrast: None,
         }
     }
 }
@@ -173,10 +301,12 @@ impl PromptContents {
                 pc: NamedColor::Red,
                 sc: NamedColor::LightBlack,
                 accent: crate::zsh::theme::color_scheme::AccentColor::Rainbow(
-                    NamedColor::FullColor((0, 255, 255)),
+                    super::rainbow::Rainbow::new(NamedColor::FullColor((0, 255, 255))),
                 ),
                 accent_which: AccentWhich::ForeGround,
             },
+            color_light: None,
+            color_dark: None,
             connection: PromptConnection::Line,
             left_segment_separators: PromptSegmentSeparators {
                 start_separator: PromptSeparation::Round,
@@ -193,6 +323,7 @@ impl PromptContents {
                 bold_separation: true,
             },
             accent_which: AccentWhich::ForeGround,
+            min_contrast: None,
         }
     }
 }
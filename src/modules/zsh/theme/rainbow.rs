@@ -0,0 +1,224 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zsh_seq::NamedColor;
+
+use super::{
+    color_parse,
+    gradient::{hsl_to_rgb, named_color_to_rgb, rgb_to_hsl},
+    named_color_serde::{self, ColorInput},
+};
+
+/// 色相を回す向き
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HueDirection {
+    /// 赤 → 黄 → 緑 → 青の順
+    #[default]
+    Clockwise,
+    /// 赤 → 紫 → 青 → 緑の順
+    Counterclockwise,
+}
+
+/// 実行時の状態から決まる色相のずれ
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DynamicOffset {
+    /// 時刻に合わせて 1 日で一周する
+    Time,
+    /// カレントディレクトリごとに決まる
+    Directory,
+    /// ホスト名ごとに決まる
+    Host,
+}
+
+/// 描画 1 回分の `time` / `directory` / `host` のずれ (度)。
+/// 区切りごとに時刻やホスト名を調べ直さないよう、描画の始めに一度だけ求めて渡す
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DynamicHues {
+    time: f32,
+    directory: f32,
+    host: f32,
+}
+
+impl DynamicHues {
+    /// 今の時刻、カレントディレクトリ、ホスト名から求める
+    pub fn now() -> Self {
+        Self {
+            time: local_seconds_of_day() as f32 / 86400.0 * 360.0,
+            directory: std::env::current_dir()
+                .map(|dir| hash_degrees(dir.to_string_lossy().as_bytes()))
+                .unwrap_or_default(),
+            host: hostname()
+                .map(|host| hash_degrees(host.as_bytes()))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// 虹の開始色からの色相のずれ。度数か `time` / `directory` / `host`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HueOffset {
+    Degrees(f32),
+    Dynamic(DynamicOffset),
+}
+
+impl Default for HueOffset {
+    fn default() -> Self {
+        Self::Degrees(0.0)
+    }
+}
+
+impl HueOffset {
+    pub fn is_zero(&self) -> bool {
+        *self == Self::Degrees(0.0)
+    }
+
+    /// 描画 1 回分のずれ `hues` を使った、現在のずれ (度)
+    pub fn degrees(self, hues: &DynamicHues) -> f32 {
+        match self {
+            Self::Degrees(degrees) => degrees,
+            Self::Dynamic(DynamicOffset::Time) => hues.time,
+            Self::Dynamic(DynamicOffset::Directory) => hues.directory,
+            Self::Dynamic(DynamicOffset::Host) => hues.host,
+        }
+    }
+}
+
+/// 開始色から色相を回していく虹色のアクセント
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rainbow {
+    /// 開始色。彩度と明度もこの色から取る
+    #[serde(with = "named_color_serde")]
+    pub color: NamedColor,
+    /// 最初から最後までに回す色相 (度)
+    #[serde(default = "full_span", skip_serializing_if = "is_full_span")]
+    pub span: f32,
+    #[serde(default, skip_serializing_if = "is_clockwise")]
+    pub direction: HueDirection,
+    /// 開始色の彩度の代わりに使う値 (0.0〜1.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saturation: Option<f32>,
+    /// 開始色の明度の代わりに使う値 (0.0〜1.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lightness: Option<f32>,
+    #[serde(default, skip_serializing_if = "HueOffset::is_zero")]
+    pub offset: HueOffset,
+}
+
+fn full_span() -> f32 {
+    360.0
+}
+
+fn is_full_span(span: &f32) -> bool {
+    *span == 360.0
+}
+
+fn is_clockwise(direction: &HueDirection) -> bool {
+    *direction == HueDirection::Clockwise
+}
+
+impl Rainbow {
+    /// `color` から一周する従来の虹
+    pub fn new(color: NamedColor) -> Self {
+        Self {
+            color,
+            span: full_span(),
+            direction: HueDirection::default(),
+            saturation: None,
+            lightness: None,
+            offset: HueOffset::default(),
+        }
+    }
+
+    /// 開始色の他に設定が無いか。無ければ従来どおり色だけを書く
+    fn is_plain(&self) -> bool {
+        is_full_span(&self.span)
+            && is_clockwise(&self.direction)
+            && self.saturation.is_none()
+            && self.lightness.is_none()
+            && self.offset.is_zero()
+    }
+
+    /// `progress` (0.0〜1.0) の位置の色。動的なずれは `hues` から取る
+    pub fn get(&self, progress: f32, hues: &DynamicHues) -> NamedColor {
+        let (r, g, b) = named_color_to_rgb(&self.color);
+        let (start_hue, saturation, lightness) = rgb_to_hsl(r, g, b);
+        let sweep = match self.direction {
+            HueDirection::Clockwise => progress * self.span,
+            HueDirection::Counterclockwise => -progress * self.span,
+        };
+        let hue = (start_hue + self.offset.degrees(hues) + sweep).rem_euclid(360.0);
+        NamedColor::FullColor(hsl_to_rgb(
+            hue,
+            self.saturation.unwrap_or(saturation).clamp(0.0, 1.0),
+            self.lightness.unwrap_or(lightness).clamp(0.0, 1.0),
+        ))
+    }
+}
+
+/// 設定が開始色だけなら `!rainbow COLOR`、それ以外は `!rainbow {color: ..., span: ...}` として書く
+pub fn serialize_rainbow<S>(rainbow: &Rainbow, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if rainbow.is_plain() {
+        named_color_serde::serialize(&rainbow.color, serializer)
+    } else {
+        rainbow.serialize(serializer)
+    }
+}
+
+pub fn deserialize_rainbow<'de, D>(deserializer: D) -> Result<Rainbow, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RainbowInput {
+        Color(ColorInput),
+        Full(Rainbow),
+    }
+    match RainbowInput::deserialize(deserializer)? {
+        RainbowInput::Color(color) => color_parse::parse(&color.into_string())
+            .map(Rainbow::new)
+            .map_err(serde::de::Error::custom),
+        RainbowInput::Full(rainbow) => Ok(rainbow),
+    }
+}
+
+/// 文字列から色相を決める。同じ文字列なら常に同じ色相になるよう FNV-1a を使う
+fn hash_degrees(bytes: &[u8]) -> f32 {
+    let hash = bytes.iter().fold(0x811c_9dc5u32, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+    });
+    (hash % 360) as f32
+}
+
+/// 現地時刻の 0 時からの秒数
+fn local_seconds_of_day() -> i64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let now = secs as libc::time_t;
+    // SAFETY: tm は整数とポインタだけの C の構造体なので、全て 0 でも有効な値になる
+    let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+    // SAFETY: `now` と `tm` はどちらも呼び出しの間有効な値を指す
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        // 現地時刻が分からなければ UTC を使う
+        return (secs % 86400) as i64;
+    }
+    (tm.tm_hour * 3600 + tm.tm_min * 60 + tm.tm_sec) as i64
+}
+
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: `buf` は書き込める buf.len() バイトの領域を指す
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
@@ -0,0 +1,488 @@
+use serde_json::{Value, json};
+
+use super::{migrate, prompt_theme::PromptTheme};
+use crate::zsh::prompt::{PromptConnection, PromptSeparation};
+
+/// `color_parse::parse` が受け付ける色の文字列の大まかな形。色名は大文字と小文字を区別しない
+const NAMED_COLOR_PATTERN: &str = r"^\s*(#([0-9A-Fa-f]{3}|[0-9A-Fa-f]{6})|\d{1,3}|[A-Za-z][A-Za-z0-9]*\([^)]*\)|[A-Za-z][A-Za-z-]*)\s*$";
+
+/// `gradient::parse_gradient_stop` が受け付ける `"COLOR:pos[:easing]"` 形式。色はパレットの参照でもよい
+const GRADIENT_STOP_PATTERN: &str = r"^\s*(#([0-9A-Fa-f]{3}|[0-9A-Fa-f]{6})|\d{1,3}|[A-Za-z][A-Za-z0-9]*\([^)]*\)|[A-Za-z][A-Za-z-]*|\$[\w-]+)\s*:\s*[-+]?(\d+\.?\d*|\.\d+)([eE][-+]?\d+)?\s*(:(linear|ease-in|ease-out|ease-in-out))?$";
+
+/// `PerSegment` の区切りの番号
+const SEPARATOR_INDEX_PATTERN: &str = r"^\d+$";
+
+/// パレットの色の参照 `$name`
+const PALETTE_REFERENCE_PATTERN: &str = r"^\$[\w-]+$";
+
+/// YAML Language Server で `!tag` を扱うための `yaml.customTags` 設定値
+pub const YAML_CUSTOM_TAGS: &[&str] = &[
+    "!single scalar",
+    "!rainbow scalar",
+    "!rainbow mapping",
+    "!gradient sequence",
+    "!gradient mapping",
+    "!cycle sequence",
+    "!perSegment mapping",
+    "!Literal mapping",
+    "!BuildIn mapping",
+    "!Shell mapping",
+    "!Pwd mapping",
+    "!Cmd mapping",
+    "!Os mapping",
+    "!Git mapping",
+    "!Time mapping",
+];
+
+pub fn print_schema() {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&theme_schema()).expect("Failed to serialize schema")
+    );
+}
+
+/// `PromptTheme` を記述する JSON Schema を生成する
+///
+/// serde_yaml は enum を `!tag` で表現するため、タグ付きの値はタグの中身だけでも、
+/// JSON と同じ `{tag: 中身}` 形式でも検証できるようにしている。
+pub fn theme_schema() -> Value {
+    let mut schema = full_schema();
+    add_partial_defs(&mut schema);
+    schema
+}
+
+fn full_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Zsh Infinite theme",
+        "description": format!(
+            "Theme file for zsh-infinite. For YAML tags such as `!rainbow`, configure yaml.customTags: {}",
+            YAML_CUSTOM_TAGS.join(", ")
+        ),
+        "if": { "required": ["extends"] },
+        "then": { "$ref": "#/$defs/PartialPromptTheme" },
+        "else": { "$ref": "#/$defs/PromptTheme" },
+        "$defs": {
+            "PromptTheme": {
+                "type": "object",
+                "properties": {
+                    "version": {
+                        "description": format!(
+                            "Layout version of this file. Files written for older versions are upgraded when loaded (current: {}).",
+                            migrate::CURRENT_VERSION
+                        ),
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": migrate::CURRENT_VERSION
+                    },
+                    "extends": {
+                        "description": format!(
+                            "Base theme: a preset ({}) or a path relative to this file. Only the overrides need to be written; lines are merged by index and a `null` line removes it.",
+                            PromptTheme::PRESETS.join(", ")
+                        ),
+                        "type": "string"
+                    },
+                    "palette": {
+                        "description": "Named colours that colour fields can reference as `$name`.",
+                        "type": "object",
+                        "additionalProperties": { "type": "string" }
+                    },
+                    "prompt_contents_list": {
+                        "description": "One entry per prompt line, top to bottom.",
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/PromptContents" }
+                    },
+                    "accent_scope": {
+                        "description": "`Line` (default) restarts the accent on every line. `Prompt` runs line 0's accent once across all lines, the bottom frame, RPROMPT and the transient prompt; `Snake` does the same but every other line runs right to left.",
+                        "enum": ["Line", "Prompt", "Snake"]
+                    },
+                    "transient_color": {
+                        "description": "Colours of the transient prompt. `pc` is used after success, `sc` after failure.",
+                        "$ref": "#/$defs/PromptColorScheme"
+                    },
+                    "transient_color_light": {
+                        "description": "Replaces `transient_color` when the terminal background is light.",
+                        "$ref": "#/$defs/PromptColorScheme"
+                    },
+                    "transient_color_dark": {
+                        "description": "Replaces `transient_color` when the terminal background is dark.",
+                        "$ref": "#/$defs/PromptColorScheme"
+                    }
+                },
+                "required": ["prompt_contents_list"]
+            },
+            "PromptContents": {
+                "type": "object",
+                "properties": {
+                    "left": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/PromptContent" }
+                    },
+                    "right": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/PromptContent" }
+                    },
+                    "color": { "$ref": "#/$defs/PromptColorScheme" },
+                    "color_light": {
+                        "description": "Replaces `color` when the terminal background is light.",
+                        "$ref": "#/$defs/PromptColorScheme"
+                    },
+                    "color_dark": {
+                        "description": "Replaces `color` when the terminal background is dark.",
+                        "$ref": "#/$defs/PromptColorScheme"
+                    },
+                    "connection": { "$ref": "#/$defs/PromptConnection" },
+                    "left_segment_separators": { "$ref": "#/$defs/PromptSegmentSeparators" },
+                    "right_segment_separators": { "$ref": "#/$defs/PromptSegmentSeparators" },
+                    "accent_which": { "$ref": "#/$defs/AccentWhich" },
+                    "min_contrast": {
+                        "description": "Minimum WCAG contrast ratio between segment text and its background. Text colours below it are lightened or darkened; text without a colour is drawn in `fg`, adjusted the same way.",
+                        "type": "number",
+                        "minimum": 1,
+                        "maximum": 21
+                    }
+                },
+                "required": [
+                    "connection",
+                    "left_segment_separators",
+                    "right_segment_separators",
+                    "accent_which"
+                ]
+            },
+            "PromptContent": {
+                "description": "A prompt segment, written as `!Literal`, `!BuildIn` or `!Shell` in YAML.",
+                "anyOf": [
+                    { "$ref": "#/$defs/LiteralContent" },
+                    { "$ref": "#/$defs/BuildInContent" },
+                    { "$ref": "#/$defs/ShellContent" },
+                    tagged("Literal", "#/$defs/LiteralContent"),
+                    tagged("BuildIn", "#/$defs/BuildInContent"),
+                    tagged("Shell", "#/$defs/ShellContent")
+                ]
+            },
+            "LiteralContent": {
+                "description": "Fixed text (`!Literal`).",
+                "type": "object",
+                "properties": {
+                    "value": { "type": "string" },
+                    "fg": { "$ref": "#/$defs/OptionalNamedColor" },
+                    "bg": { "$ref": "#/$defs/OptionalNamedColor" }
+                },
+                "required": ["value"],
+                "additionalProperties": false
+            },
+            "BuildInContent": {
+                "description": "A segment rendered inside zsh-infinite (`!BuildIn`).",
+                "type": "object",
+                "properties": {
+                    "command": { "$ref": "#/$defs/BuildInCommand" }
+                },
+                "required": ["command"],
+                "additionalProperties": false
+            },
+            "ShellContent": {
+                "description": "The trimmed stdout of an external command (`!Shell`). Arguments are expanded with environment variables.",
+                "type": "object",
+                "properties": {
+                    "cmd": { "type": "string" },
+                    "args": { "type": "array", "items": { "type": "string" } },
+                    "envs": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" }
+                    },
+                    "fg": { "$ref": "#/$defs/OptionalNamedColor" },
+                    "bg": { "$ref": "#/$defs/OptionalNamedColor" }
+                },
+                "required": ["cmd"],
+                "additionalProperties": false
+            },
+            "BuildInCommand": {
+                "description": "A built-in segment, written as `!Pwd`, `!Cmd`, `!Os`, `!Git` or `!Time` in YAML.",
+                "anyOf": build_in_command_schemas()
+            },
+            "PromptColorScheme": {
+                "type": "object",
+                "properties": {
+                    "bg": { "description": "Segment background.", "$ref": "#/$defs/NamedColor" },
+                    "fg": { "description": "Segment foreground.", "$ref": "#/$defs/NamedColor" },
+                    "pc": { "description": "Primary colour, used for the connection line.", "$ref": "#/$defs/NamedColor" },
+                    "sc": { "description": "Secondary colour, used for the frame.", "$ref": "#/$defs/NamedColor" },
+                    "accent": { "$ref": "#/$defs/AccentColor" },
+                    "accent_which": { "$ref": "#/$defs/AccentWhich" }
+                },
+                "required": ["bg", "fg", "pc", "sc", "accent", "accent_which"]
+            },
+            "AccentColor": {
+                "description": "Separator colour: `!single COLOR`, `!rainbow COLOR` (hue sweep starting at COLOR; also `!rainbow {color: COLOR, span: 60, ...}`) `!gradient [\"COLOR:pos\", ...]` (also `!gradient {interpolation: oklch, stops: [...]}`), `!cycle [COLOR, ...]` (one colour per separator, repeating) or `!perSegment {0: COLOR, 3: COLOR}` (separator 0-2 use the first colour, 3 onwards the second).",
+                "anyOf": [
+                    { "$ref": "#/$defs/NamedColor" },
+                    { "$ref": "#/$defs/Gradient" },
+                    { "$ref": "#/$defs/Rainbow" },
+                    { "$ref": "#/$defs/Cycle" },
+                    { "$ref": "#/$defs/PerSegment" },
+                    tagged("single", "#/$defs/NamedColor"),
+                    tagged("rainbow", "#/$defs/Rainbow"),
+                    tagged("gradient", "#/$defs/Gradient"),
+                    tagged("cycle", "#/$defs/Cycle"),
+                    tagged("perSegment", "#/$defs/PerSegment")
+                ]
+            },
+            "Rainbow": {
+                "anyOf": [
+                    { "$ref": "#/$defs/NamedColor" },
+                    {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {
+                            "color": { "description": "Start colour.", "$ref": "#/$defs/NamedColor" },
+                            "span": { "description": "Degrees of hue swept from the first to the last separator (default 360).", "type": "number" },
+                            "direction": { "enum": ["clockwise", "counterclockwise"] },
+                            "saturation": { "description": "Overrides the start colour's HSL saturation.", "type": "number", "minimum": 0, "maximum": 1 },
+                            "lightness": { "description": "Overrides the start colour's HSL lightness.", "type": "number", "minimum": 0, "maximum": 1 },
+                            "offset": {
+                                "description": "Hue shift in degrees, or `time` (one turn per day), `directory` or `host` (a hue derived from the current directory or host name).",
+                                "anyOf": [
+                                    { "type": "number" },
+                                    { "enum": ["time", "directory", "host"] }
+                                ]
+                            }
+                        },
+                        "required": ["color"]
+                    }
+                ]
+            },
+            "Gradient": {
+                "anyOf": [
+                    {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/GradientStop" }
+                    },
+                    {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {
+                            "interpolation": {
+                                "description": "How colours between stops are mixed. `srgb` (default) mixes the raw values; `oklch` keeps long gradients vivid.",
+                                "enum": ["srgb", "linear-rgb", "hsl", "oklab", "oklch"]
+                            },
+                            "stops": {
+                                "type": "array",
+                                "items": { "$ref": "#/$defs/GradientStop" }
+                            }
+                        },
+                        "required": ["stops"]
+                    }
+                ]
+            },
+            "Cycle": {
+                "type": "array",
+                "items": { "$ref": "#/$defs/NamedColor" },
+                "minItems": 1
+            },
+            "PerSegment": {
+                "description": "Colours keyed by separator number, counted from 0 at the left edge of the line. A separator without its own number uses the closest smaller one.",
+                "type": "object",
+                "propertyNames": { "pattern": SEPARATOR_INDEX_PATTERN },
+                "additionalProperties": { "$ref": "#/$defs/NamedColor" },
+                "minProperties": 1
+            },
+            "GradientStop": {
+                "description": "`COLOR:pos` with any colour notation or a palette reference `$name`, where pos is between 0.0 and 1.0, optionally followed by `:ease-in`, `:ease-out` or `:ease-in-out` for the stretch up to the next stop.",
+                "type": "string",
+                "pattern": GRADIENT_STOP_PATTERN
+            },
+            "NamedColor": {
+                "description": "A terminal colour name (`Red`, `LightRed`, `bright-red`), a CSS colour name, `#RGB`, `#RRGGBB`, `rgb(r, g, b)`, `hsl(h, s%, l%)`, a 0-255 code, `Code256(n)`, `FullColor(r,g,b)` or a palette reference `$name`.",
+                "type": "string",
+                "anyOf": [
+                    { "pattern": NAMED_COLOR_PATTERN },
+                    { "pattern": PALETTE_REFERENCE_PATTERN }
+                ]
+            },
+            "OptionalNamedColor": {
+                "description": "A colour, or `None` to keep the surrounding colour.",
+                "anyOf": [
+                    { "$ref": "#/$defs/NamedColor" },
+                    { "type": "string", "pattern": "^[Nn][Oo][Nn][Ee]$" }
+                ]
+            },
+            "AccentWhich": {
+                "description": "Whether the accent colours the separators (ForeGround) or the segment backgrounds (BackGround).",
+                "enum": ["ForeGround", "BackGround"]
+            },
+            "PromptConnection": {
+                "enum": PromptConnection::ALL
+                    .iter()
+                    .map(|c| format!("{:?}", c))
+                    .collect::<Vec<_>>()
+            },
+            "PromptSeparation": {
+                "enum": PromptSeparation::ALL
+                    .iter()
+                    .map(|s| format!("{:?}", s))
+                    .collect::<Vec<_>>()
+            },
+            "PromptSegmentSeparators": {
+                "type": "object",
+                "properties": {
+                    "start_separator": { "$ref": "#/$defs/PromptSeparation" },
+                    "mid_separator": { "$ref": "#/$defs/PromptSeparation" },
+                    "end_separator": { "$ref": "#/$defs/PromptSeparation" },
+                    "edge_cap": { "type": "boolean" },
+                    "bold_separation": { "type": "boolean" }
+                },
+                "required": [
+                    "start_separator",
+                    "mid_separator",
+                    "end_separator",
+                    "edge_cap",
+                    "bold_separation"
+                ]
+            }
+        }
+    })
+}
+
+/// `extends` を使うファイル向けに、必須項目を外した `Partial〜` 定義を追加する
+fn add_partial_defs(schema: &mut Value) {
+    fn relax(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                // `{tag: 中身}` の tag まで外すと、空のマッピングがどのバリアントにも当てはまってしまう
+                if !is_tagged(map) {
+                    map.remove("required");
+                }
+                if let Some(Value::String(reference)) = map.get_mut("$ref") {
+                    *reference = reference.replace("#/$defs/", "#/$defs/Partial");
+                }
+                map.values_mut().for_each(relax);
+            }
+            Value::Array(items) => items.iter_mut().for_each(relax),
+            _ => {}
+        }
+    }
+
+    let Some(defs) = schema.get_mut("$defs").and_then(Value::as_object_mut) else {
+        return;
+    };
+    let mut partial: Vec<(String, Value)> = defs
+        .iter()
+        .map(|(name, def)| {
+            let mut def = def.clone();
+            relax(&mut def);
+            (format!("Partial{}", name), def)
+        })
+        .collect();
+    // 行を null にするとその行を取り除ける
+    if let Some((_, theme)) = partial
+        .iter_mut()
+        .find(|(name, _)| name == "PartialPromptTheme")
+    {
+        theme["properties"]["prompt_contents_list"]["items"] = json!({
+            "anyOf": [{ "$ref": "#/$defs/PartialPromptContents" }, { "type": "null" }]
+        });
+    }
+    defs.extend(partial);
+}
+
+/// `tagged` で作った `{tag: 中身}` 形式かどうか
+fn is_tagged(map: &serde_json::Map<String, Value>) -> bool {
+    let Some(Value::Array(required)) = map.get("required") else {
+        return false;
+    };
+    let Some(Value::Object(properties)) = map.get("properties") else {
+        return false;
+    };
+    map.get("additionalProperties") == Some(&Value::Bool(false))
+        && required.len() == 1
+        && properties.len() == 1
+        && required[0]
+            .as_str()
+            .is_some_and(|tag| properties.contains_key(tag))
+}
+
+/// JSON などでの `{tag: 中身}` 形式
+fn tagged(tag: &str, reference: &str) -> Value {
+    json!({
+        "type": "object",
+        "properties": { tag: { "$ref": reference } },
+        "required": [tag],
+        "additionalProperties": false
+    })
+}
+
+fn build_in_command_schemas() -> Vec<Value> {
+    let color = json!({ "type": ["string", "null"] });
+    let commands = [
+        (
+            "Pwd",
+            "Current directory.",
+            json!({ "color": color }),
+            json!([]),
+        ),
+        (
+            "Cmd",
+            "Status and duration of the last command.",
+            json!({
+                "last_status": { "type": "string" },
+                "last_command_executed": { "type": ["string", "null"] },
+                "color": color
+            }),
+            json!(["last_status"]),
+        ),
+        (
+            "Os",
+            "Operating system icon.",
+            json!({ "color": color }),
+            json!([]),
+        ),
+        (
+            "Git",
+            "Repository status.",
+            json!({
+                "path": { "type": ["string", "null"] },
+                "options": {
+                    "type": "object",
+                    "properties": {
+                        "default_color_option": {},
+                        "git_icon_color_option": {},
+                        "branch_color_option": {},
+                        "staged_color_option": {},
+                        "unstaged_color_option": {},
+                        "untracked_color_option": {},
+                        "conflict_color_option": {},
+                        "stashed_color_option": {},
+                        "clean_color_option": {},
+                        "ahead_color_option": {},
+                        "behind_color_option": {}
+                    }
+                }
+            }),
+            json!([]),
+        ),
+        (
+            "Time",
+            "Current time.",
+            json!({ "color": color }),
+            json!([]),
+        ),
+    ];
+
+    let mut schemas = Vec::new();
+    for (name, description, properties, required) in commands {
+        let body = json!({
+            "description": format!("{} (`!{}`)", description, name),
+            "type": "object",
+            "properties": properties,
+            "required": required
+        });
+        schemas.push(body.clone());
+        schemas.push(json!({
+            "type": "object",
+            "properties": { name: body },
+            "required": [name],
+            "additionalProperties": false
+        }));
+    }
+    schemas
+}
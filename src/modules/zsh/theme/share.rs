@@ -0,0 +1,146 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use flate2::{Compression, read::DeflateDecoder, write::DeflateEncoder};
+use serde_yaml::Value;
+use std::{
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    manager::{self, ThemeFormat},
+    migrate, preview,
+    prompt_theme::{PromptContent, PromptTheme},
+};
+
+/// 共有文字列の先頭に付ける印。形式を変えたら番号を上げる
+const PREFIX: &str = "zi1:";
+/// 展開後の大きさの上限
+const MAX_SIZE: u64 = 1 << 20;
+
+/// テーマを圧縮して base64 にした共有文字列にする。`extends` は解決済みの内容で書き出す
+pub fn encode(theme: &PromptTheme) -> io::Result<String> {
+    let mut theme = theme.clone();
+    theme.extends = None;
+    let json = ThemeFormat::Json
+        .serialize(&theme)
+        .map_err(io::Error::other)?;
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(json.as_bytes())?;
+    Ok(format!(
+        "{}{}",
+        PREFIX,
+        URL_SAFE_NO_PAD.encode(encoder.finish()?)
+    ))
+}
+
+/// 共有文字列からテーマを読み込む。古い版の共有文字列は現在のレイアウトに変換する
+pub fn decode(share: &str) -> io::Result<PromptTheme> {
+    let data = share.trim().strip_prefix(PREFIX).ok_or_else(|| {
+        io::Error::other(format!("not a share string (expected `{}...`)", PREFIX))
+    })?;
+    let compressed = URL_SAFE_NO_PAD
+        .decode(data.trim_end_matches('='))
+        .map_err(|e| io::Error::other(format!("invalid share string: {}", e)))?;
+    let mut json = String::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_SIZE)
+        .read_to_string(&mut json)
+        .map_err(|e| io::Error::other(format!("invalid share string: {}", e)))?;
+
+    let format = ThemeFormat::Json;
+    let value: Value = format.parse(&json).map_err(io::Error::other)?;
+    let mut value = migrate::migrate(value, format)
+        .map_err(io::Error::other)?
+        .value;
+    // 共有文字列から手元のファイルを参照させない
+    if let Value::Mapping(map) = &mut value {
+        map.remove("extends");
+    }
+    format.from_value(value).map_err(io::Error::other)
+}
+
+/// テーマを共有文字列にして表示する
+pub fn export(path: Option<&Path>) -> io::Result<()> {
+    let theme = manager::load_theme_from(path)?;
+    println!("{}", encode(&theme)?);
+    Ok(())
+}
+
+/// 共有文字列のテーマを表示して確認し、`out` か使用中のテーマファイルに保存する。
+/// 取り消した場合は `None` を返す
+pub fn import(share: &str, out: Option<&Path>) -> io::Result<Option<PathBuf>> {
+    confirm_and_save(decode(share)?, out)
+}
+
+/// 外から来たテーマを表示して確認し、`out` か使用中のテーマファイルに保存する。
+/// `Shell` セグメントは一覧を見せて明示的に許可された場合だけ残す。
+/// 取り消した場合は `None` を返す
+pub fn confirm_and_save(mut theme: PromptTheme, out: Option<&Path>) -> io::Result<Option<PathBuf>> {
+    println!("{}", preview::render(&theme));
+
+    let commands = shell_commands(&theme);
+    if !commands.is_empty() {
+        println!("This theme runs the following commands on every prompt:");
+        for command in &commands {
+            println!("  - {}", command);
+        }
+        let allowed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Enable these commands?")
+            .default(false)
+            .interact()
+            .unwrap_or(false);
+        if !allowed {
+            for contents in &mut theme.prompt_contents_list {
+                for side in [&mut contents.left, &mut contents.right] {
+                    side.retain(|content| !matches!(content, PromptContent::Shell { .. }));
+                }
+            }
+            println!("The commands were removed from the theme.");
+        }
+    }
+
+    let apply = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt("Apply this theme?")
+        .default(true)
+        .interact()
+        .unwrap_or(false);
+    if !apply {
+        return Ok(None);
+    }
+    let out = match out {
+        Some(out) => out.to_path_buf(),
+        None => manager::get_theme_file_path().ok_or_else(|| {
+            io::Error::other("Could not determine project directories to save theme file.")
+        })?,
+    };
+    manager::save_theme_to(&theme, &out)?;
+    Ok(Some(out))
+}
+
+/// `Shell` セグメントで実行されるコマンドの一覧
+fn shell_commands(theme: &PromptTheme) -> Vec<String> {
+    let mut commands = Vec::new();
+    for (i, contents) in theme.prompt_contents_list.iter().enumerate() {
+        for (side, list) in [("left", &contents.left), ("right", &contents.right)] {
+            for content in list {
+                let PromptContent::Shell {
+                    cmd, args, envs, ..
+                } = content
+                else {
+                    continue;
+                };
+                let mut envs: Vec<_> = envs.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                envs.sort();
+                let command: Vec<&str> = envs
+                    .iter()
+                    .map(String::as_str)
+                    .chain(std::iter::once(cmd.as_str()))
+                    .chain(args.iter().map(String::as_str))
+                    .collect();
+                commands.push(format!("line {} ({}): {}", i + 1, side, command.join(" ")));
+            }
+        }
+    }
+    commands
+}
@@ -0,0 +1,367 @@
+use clap::ValueEnum;
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use regex::Regex;
+use serde_yaml::Value;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use zsh_seq::NamedColor;
+
+use super::{
+    color_depth::ColorDepth,
+    color_parse,
+    color_scheme::{AccentColor, PromptColorScheme},
+    contrast::contrast_ratio,
+    gradient::{Gradient, GradientStop, named_color_to_rgb},
+    manager, preview,
+};
+
+/// 端末の配色ファイルの形式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PaletteFormat {
+    /// base16 / base24 の YAML
+    Base16,
+    /// Alacritty の TOML
+    Alacritty,
+    /// Kitty の .conf
+    Kitty,
+    /// iTerm2 の .itermcolors
+    Iterm2,
+}
+
+impl PaletteFormat {
+    /// 拡張子から形式を決める
+    fn detect(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "yaml" | "yml" => Some(Self::Base16),
+            "toml" => Some(Self::Alacritty),
+            "conf" => Some(Self::Kitty),
+            "itermcolors" | "plist" => Some(Self::Iterm2),
+            _ => None,
+        }
+    }
+}
+
+/// 端末の 16 色の名前。明るい色は `bright-` を付けてパレットに入れる
+const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// base16 の base00〜base0F から端末の 16 色への対応 (base16-shell と同じ)
+const BASE16_ANSI: [&str; 16] = [
+    "base00", "base08", "base0B", "base0A", "base0D", "base0E", "base0C", "base05", "base03",
+    "base08", "base0B", "base0A", "base0D", "base0E", "base0C", "base07",
+];
+
+/// base24 で明るい赤〜紫に使う色。端末の 9〜14 番を置き換える
+const BASE24_BRIGHT: [&str; 6] = ["base12", "base14", "base13", "base16", "base17", "base15"];
+
+/// 端末の配色ファイルから読んだ色
+#[derive(Debug, Default)]
+pub struct TerminalColors {
+    pub background: Option<(u8, u8, u8)>,
+    pub foreground: Option<(u8, u8, u8)>,
+    pub cursor: Option<(u8, u8, u8)>,
+    /// 端末の 0〜15 番の色
+    pub ansi: [Option<(u8, u8, u8)>; 16],
+}
+
+impl TerminalColors {
+    fn ansi(&self, i: usize) -> (u8, u8, u8) {
+        // 明るい色が無ければ通常の色を使う
+        self.ansi[i]
+            .or_else(|| self.ansi[i % 8])
+            .unwrap_or_default()
+    }
+
+    fn background(&self) -> (u8, u8, u8) {
+        self.background.unwrap_or_else(|| self.ansi(0))
+    }
+
+    fn foreground(&self) -> (u8, u8, u8) {
+        self.foreground.unwrap_or_else(|| self.ansi(7))
+    }
+
+    /// パレットに入れる名前と色
+    pub fn palette_entries(&self) -> Vec<(String, (u8, u8, u8))> {
+        let mut entries = vec![
+            ("background".to_string(), self.background()),
+            ("foreground".to_string(), self.foreground()),
+        ];
+        if let Some(cursor) = self.cursor {
+            entries.push(("cursor".to_string(), cursor));
+        }
+        for (i, name) in ANSI_NAMES.iter().enumerate() {
+            entries.push((name.to_string(), self.ansi(i)));
+        }
+        for (i, name) in ANSI_NAMES.iter().enumerate() {
+            entries.push((format!("bright-{}", name), self.ansi(i + 8)));
+        }
+        entries
+    }
+
+    /// 端末の色から作るプロンプトの配色。
+    /// セグメントの背景は黒と白の 4 色のうち端末の背景と違うもので、文字色が最も読みやすいものにする。
+    /// 明るい配色では白の方が暗いこともあるので、明暗ではなくコントラスト比で選ぶ
+    pub fn color_scheme(&self) -> PromptColorScheme {
+        let background = self.background();
+        let foreground = self.foreground();
+        let bg = [0, 8, 7, 15]
+            .into_iter()
+            .map(|i| self.ansi(i))
+            .filter(|&color| color != background)
+            .max_by(|&a, &b| {
+                contrast_ratio(a, foreground).total_cmp(&contrast_ratio(b, foreground))
+            })
+            .unwrap_or(background);
+        let stops = [4, 5, 1]
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| {
+                GradientStop::new(NamedColor::FullColor(self.ansi(color)), i as f32 / 2.0)
+            })
+            .collect();
+        PromptColorScheme {
+            bg: NamedColor::FullColor(bg),
+            fg: NamedColor::FullColor(foreground),
+            pc: NamedColor::FullColor(self.ansi(6)),
+            sc: NamedColor::FullColor(self.ansi(8)),
+            accent: AccentColor::Gradient(Gradient::new(stops)),
+            accent_which: Default::default(),
+        }
+    }
+}
+
+/// `#RRGGBB`、`RRGGBB`、`0xRRGGBB` のような色を読む
+fn parse_hex(s: &str) -> Option<(u8, u8, u8)> {
+    let s = s.trim().trim_matches(|c| c == '"' || c == '\'');
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .or_else(|| s.strip_prefix('#'))
+        .unwrap_or(s);
+    color_parse::parse(&format!("#{}", hex))
+        .ok()
+        .map(|color| named_color_to_rgb(&color))
+}
+
+fn parse_base16(content: &str) -> Result<TerminalColors, String> {
+    let value: Value = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+    // tinted-theming の新しい形式は色を `palette` の下に書く
+    let map = value
+        .get("palette")
+        .unwrap_or(&value)
+        .as_mapping()
+        .ok_or("expected a mapping with base00 to base0F")?;
+    let get = |name: &str| {
+        map.iter()
+            .find(|(key, _)| {
+                key.as_str()
+                    .is_some_and(|key| key.eq_ignore_ascii_case(name))
+            })
+            .and_then(|(_, value)| match value {
+                Value::String(s) => parse_hex(s),
+                // 引用符の無い `000000` のような値は数として読まれる
+                Value::Number(n) => n.as_u64().and_then(|n| parse_hex(&format!("{:06}", n))),
+                _ => None,
+            })
+    };
+    let mut colors = TerminalColors {
+        background: get("base00"),
+        foreground: get("base05"),
+        ..TerminalColors::default()
+    };
+    for (i, name) in BASE16_ANSI.iter().enumerate() {
+        colors.ansi[i] = get(name);
+    }
+    for (i, name) in BASE24_BRIGHT.iter().enumerate() {
+        if let Some(color) = get(name) {
+            colors.ansi[i + 9] = Some(color);
+        }
+    }
+    Ok(colors)
+}
+
+fn parse_alacritty(content: &str) -> Result<TerminalColors, String> {
+    let value: toml::Value = toml::from_str(content).map_err(|e| e.to_string())?;
+    let colors = value.get("colors").ok_or("no [colors] section found")?;
+    let get = |table: &str, key: &str| {
+        colors
+            .get(table)
+            .and_then(|table| table.get(key))
+            .and_then(|value| value.as_str())
+            .and_then(parse_hex)
+    };
+    let mut terminal = TerminalColors {
+        background: get("primary", "background"),
+        foreground: get("primary", "foreground"),
+        cursor: get("cursor", "cursor"),
+        ..TerminalColors::default()
+    };
+    for (i, name) in ANSI_NAMES.iter().enumerate() {
+        terminal.ansi[i] = get("normal", name);
+        terminal.ansi[i + 8] = get("bright", name);
+    }
+    Ok(terminal)
+}
+
+fn parse_kitty(content: &str) -> TerminalColors {
+    let mut colors = TerminalColors::default();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let (Some(key), Some(value)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let Some(color) = parse_hex(value) else {
+            continue;
+        };
+        match key {
+            "background" => colors.background = Some(color),
+            "foreground" => colors.foreground = Some(color),
+            "cursor" => colors.cursor = Some(color),
+            key => {
+                if let Some(i) = key
+                    .strip_prefix("color")
+                    .and_then(|i| i.parse::<usize>().ok())
+                    .filter(|i| *i < 16)
+                {
+                    colors.ansi[i] = Some(color);
+                }
+            }
+        }
+    }
+    colors
+}
+
+fn parse_iterm2(content: &str) -> TerminalColors {
+    let entry = Regex::new(r"(?s)<key>([^<]+)</key>\s*<dict>(.*?)</dict>").unwrap();
+    let component =
+        Regex::new(r"<key>(Red|Green|Blue) Component</key>\s*<real>([^<]+)</real>").unwrap();
+    let mut colors = TerminalColors::default();
+    for captures in entry.captures_iter(content) {
+        let mut rgb = [None; 3];
+        for component in component.captures_iter(&captures[2]) {
+            let i = match &component[1] {
+                "Red" => 0,
+                "Green" => 1,
+                _ => 2,
+            };
+            rgb[i] = component[2]
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+        let [Some(r), Some(g), Some(b)] = rgb else {
+            continue;
+        };
+        let color = Some((r, g, b));
+        match captures[1].trim() {
+            "Background Color" => colors.background = color,
+            "Foreground Color" => colors.foreground = color,
+            "Cursor Color" => colors.cursor = color,
+            key => {
+                if let Some(i) = key
+                    .strip_prefix("Ansi ")
+                    .and_then(|key| key.strip_suffix(" Color"))
+                    .and_then(|i| i.parse::<usize>().ok())
+                    .filter(|i| *i < 16)
+                {
+                    colors.ansi[i] = color;
+                }
+            }
+        }
+    }
+    colors
+}
+
+/// 配色ファイルを読む。`format` が無ければ拡張子から決める
+pub fn read(path: &Path, format: Option<PaletteFormat>) -> io::Result<TerminalColors> {
+    let format = format
+        .or_else(|| PaletteFormat::detect(path))
+        .ok_or_else(|| {
+            io::Error::other(format!(
+                "Cannot tell the format of {:?} from its extension; pass --format",
+                path
+            ))
+        })?;
+    let content = fs::read_to_string(path)?;
+    let colors = match format {
+        PaletteFormat::Base16 => parse_base16(&content),
+        PaletteFormat::Alacritty => parse_alacritty(&content),
+        PaletteFormat::Kitty => Ok(parse_kitty(&content)),
+        PaletteFormat::Iterm2 => Ok(parse_iterm2(&content)),
+    }
+    .map_err(|e| io::Error::other(format!("Failed to read {:?}: {}", path, e)))?;
+    // 通常の 8 色が揃っていなければ配色として使えない
+    if colors.ansi[..8].iter().any(Option::is_none) {
+        return Err(io::Error::other(format!(
+            "{:?} does not define all 8 normal terminal colours ({:?} format)",
+            path, format
+        )));
+    }
+    Ok(colors)
+}
+
+/// 配色ファイルの色をパレットに加え、全ての行の配色をその色で作り直す。
+/// 見本を表示し、確認してから保存する。`out` が無ければ使用中のテーマファイルに保存する
+pub fn run(
+    path: &Path,
+    format: Option<PaletteFormat>,
+    out: Option<&Path>,
+    yes: bool,
+) -> io::Result<Option<PathBuf>> {
+    let colors = read(path, format)?;
+    let scheme = colors.color_scheme();
+
+    let mut theme = manager::load_theme_from(None)?;
+    let entries = colors.palette_entries();
+    for (name, (r, g, b)) in &entries {
+        theme
+            .palette
+            .insert(name.clone(), format!("#{:02X}{:02X}{:02X}", r, g, b));
+    }
+    for contents in &mut theme.prompt_contents_list {
+        contents.color = PromptColorScheme {
+            accent_which: contents.color.accent_which,
+            ..scheme.clone()
+        };
+        // 明暗ごとの配色が残っていると取り込んだ配色より優先されてしまう
+        contents.color_light = None;
+        contents.color_dark = None;
+    }
+    theme.transient_color.pc = NamedColor::FullColor(colors.ansi(2));
+    theme.transient_color.sc = NamedColor::FullColor(colors.ansi(1));
+    theme.transient_color_light = None;
+    theme.transient_color_dark = None;
+
+    let depth = ColorDepth::detect();
+    let swatches = entries
+        .iter()
+        .map(|(_, rgb)| {
+            let color = depth.quantize(NamedColor::FullColor(*rgb));
+            format!("{}  ", preview::ansi(color, true))
+        })
+        .collect::<String>();
+    println!("Palette: {} colours\n{}\x1b[0m", entries.len(), swatches);
+    println!("{}", preview::render(&theme));
+    let apply = yes
+        || Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Apply these colours?")
+            .default(true)
+            .interact()
+            .unwrap_or(false);
+    if !apply {
+        return Ok(None);
+    }
+    let out = match out {
+        Some(out) => out.to_path_buf(),
+        None => manager::get_theme_file_path().ok_or_else(|| {
+            io::Error::other("Could not determine project directories to save theme file.")
+        })?,
+    };
+    manager::save_theme_to(&theme, &out)?;
+    Ok(Some(out))
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    args::TrustCommands,
+    zsh::theme::{local::LOCAL_THEME_FILE, manager},
+};
+
+/// 信頼済みファイルの一覧を保存するファイル名 (設定ディレクトリからの相対)
+const TRUST_FILE: &str = "trust.yaml";
+
+/// 信頼済みのディレクトリごとの設定ファイル。
+/// 内容が変わると `hash` が合わなくなり、再度 `trust add` するまで信頼されない
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TrustEntry {
+    pub path: PathBuf,
+    pub hash: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    #[serde(default)]
+    pub trusted: Vec<TrustEntry>,
+}
+
+/// ファイルの状態
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrustStatus {
+    Trusted,
+    /// 信頼した後に内容が変わった
+    Modified,
+    /// 信頼したファイルが無くなった
+    Missing,
+    Untrusted,
+}
+
+impl TrustStore {
+    fn path() -> Option<PathBuf> {
+        manager::get_config_dir().map(|dir| dir.join(TRUST_FILE))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_yaml::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("Failed to parse trust store {:?}: {}", path, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path().ok_or_else(|| {
+            io::Error::other("Could not determine project directories to save trust store.")
+        })?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_yaml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    pub fn status(&self, path: &Path) -> TrustStatus {
+        let path = canonical(path);
+        let Some(entry) = self.trusted.iter().find(|entry| entry.path == path) else {
+            return TrustStatus::Untrusted;
+        };
+        match file_hash(&path) {
+            Ok(hash) if hash == entry.hash => TrustStatus::Trusted,
+            Ok(_) => TrustStatus::Modified,
+            Err(_) => TrustStatus::Missing,
+        }
+    }
+}
+
+/// ディレクトリごとの設定ファイルを読んでよいか。
+/// 信頼済みで、かつ信頼した時から内容が変わっていない場合のみ `true`
+pub fn is_trusted(path: &Path) -> bool {
+    TrustStore::load().status(path) == TrustStatus::Trusted
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    })
+}
+
+fn file_hash(path: &Path) -> io::Result<String> {
+    let content = fs::read(path)?;
+    Ok(format!("{:x}", Sha256::digest(&content)))
+}
+
+/// ディレクトリが渡されたらその中の設定ファイルを指す
+fn config_file(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join(LOCAL_THEME_FILE)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+pub fn run(command: TrustCommands) {
+    let result = match command {
+        TrustCommands::Add { path } => add(&path),
+        TrustCommands::Remove { path } => remove(&path),
+        TrustCommands::List => {
+            list();
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn add(path: &Path) -> io::Result<()> {
+    let path = canonical(&config_file(path));
+    let hash = file_hash(&path)
+        .map_err(|e| io::Error::other(format!("Failed to read {:?}: {}", path, e)))?;
+    let mut store = TrustStore::load();
+    store.trusted.retain(|entry| entry.path != path);
+    store.trusted.push(TrustEntry {
+        path: path.clone(),
+        hash,
+    });
+    store.save()?;
+    eprintln!("Trusted {:?}.", path);
+    Ok(())
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    let path = canonical(&config_file(path));
+    let mut store = TrustStore::load();
+    let before = store.trusted.len();
+    store.trusted.retain(|entry| entry.path != path);
+    if store.trusted.len() == before {
+        return Err(io::Error::other(format!("{:?} is not trusted.", path)));
+    }
+    store.save()?;
+    eprintln!("Removed {:?} from trusted files.", path);
+    Ok(())
+}
+
+fn list() {
+    let store = TrustStore::load();
+    if store.trusted.is_empty() {
+        println!("No trusted files.");
+        return;
+    }
+    for entry in &store.trusted {
+        let status = match store.status(&entry.path) {
+            TrustStatus::Trusted => "trusted",
+            TrustStatus::Modified => "modified since trusted",
+            TrustStatus::Missing => "missing",
+            TrustStatus::Untrusted => "untrusted",
+        };
+        println!("{} ({})", entry.path.display(), status);
+    }
+}
@@ -3,43 +3,84 @@ use tokio::runtime::Runtime;
 use zsh_system::{Features, ZshModule, ZshParameter, ZshResult, export_module};
 
 mod args;
-use crate::{args::PromptType, zsh};
+use crate::{
+    args::PromptType,
+    zsh::{
+        self,
+        theme::{
+            appearance::{self, Appearance},
+            color_depth::ColorDepth,
+        },
+    },
+};
 use args::ZmodArgs;
 #[derive(Default)]
 struct ZshInfinite {
     rt: Option<Runtime>,
     old_prompt: String,
     old_rprompt: String,
+    /// 端末に問い合わせた背景の明暗。問い合わせはセッションで一度だけ行う
+    background: Option<Option<Appearance>>,
 }
 
 impl ZshInfinite {
+    /// zsh 側の `ZSH_INFINITE_CONFIG` を反映する。export されていなくても見えるよう、シェル変数から読む
+    fn sync_config_path(&self) {
+        let path = ZshParameter::get_str(zsh::theme::manager::CONFIG_ENV)
+            .and_then(|path| zsh::theme::manager::config_path_from_env(&path));
+        zsh::theme::manager::set_config_path(path);
+    }
+    /// 端末の色数。環境変数ではなくシェル変数から読み、シェル内での変更も反映する
+    fn color_depth(&self) -> ColorDepth {
+        ColorDepth::detect_with(|name| {
+            ZshParameter::get_str(name).filter(|value| !value.is_empty())
+        })
+    }
+    /// 描画に使う背景の明暗を決める。環境変数はシェル変数から読み、シェル内での変更も反映する
+    fn sync_appearance(&mut self) {
+        let queried = *self
+            .background
+            .get_or_insert_with(appearance::query_background);
+        appearance::set_appearance(appearance::detect_with(
+            |name| ZshParameter::get_str(name).filter(|value| !value.is_empty()),
+            queried,
+        ));
+    }
+    /// 端末の背景をもう一度問い合わせる。端末の配色を切り替えた後に使う
+    pub fn refresh_background(&mut self) -> ZshResult {
+        self.background = None;
+        self.precmd()
+    }
     pub fn precmd(&mut self) -> ZshResult {
+        self.sync_config_path();
+        self.sync_appearance();
         if self.rt.is_none() {
             self.rt = Some(Runtime::new().unwrap());
         }
         let rt = self.rt.as_ref().unwrap();
 
-        let left_prompt = rt.block_on(async { zsh::build_prompt(&PromptType::Left).await.build() });
-        let right_prompt =
-            rt.block_on(async { zsh::build_prompt(&PromptType::Right).await.build() });
+        let depth = self.color_depth();
+        let left_prompt = rt.block_on(zsh::render_prompt(&PromptType::Left, depth));
+        let right_prompt = rt.block_on(zsh::render_prompt(&PromptType::Right, depth));
         ZshParameter::set_str("PROMPT", &left_prompt)?;
         ZshParameter::set_str("RPROMPT", &right_prompt)?;
         Ok(())
     }
     pub fn line_finish(&mut self) -> ZshResult {
+        self.sync_appearance();
         if self.rt.is_none() {
             self.rt = Some(Runtime::new().unwrap());
         }
         let rt = self.rt.as_ref().unwrap();
 
+        self.sync_config_path();
         let exit_code = ZshParameter::get_int("?") as i32;
-        let transient_prompt = rt.block_on(async {
-            zsh::build_prompt(&PromptType::Transient {
+        let transient_prompt = rt.block_on(zsh::render_prompt(
+            &PromptType::Transient {
                 exit_code: Some(exit_code),
-            })
-            .await
-            .build()
-        });
+            },
+            self.color_depth(),
+        ));
         ZshParameter::set_str("PROMPT", &transient_prompt)?;
         ZshParameter::set_str("RPROMPT", "")?;
         Ok(())
@@ -15,6 +15,7 @@ pub enum Commands {
     Store { key: String, value: String },
     Precmd,
     LineFinish,
+    RefreshBackground,
     Cleanup,
 }
 
@@ -34,6 +35,9 @@ impl ZmodArgs {
             Commands::LineFinish => {
                 ZshInfinite::with_instance(|zsh_infinite| zsh_infinite.line_finish())?
             }
+            Commands::RefreshBackground => {
+                ZshInfinite::with_instance(|zsh_infinite| zsh_infinite.refresh_background())?
+            }
             Commands::Cleanup => {
                 self.perform_cleanup()?;
             }